@@ -4,6 +4,8 @@ init().then(() => {
     let svgContainer = document.querySelector("#svg-container");
     let runButton = document.querySelector("#start");
     let terminateButton = document.querySelector("#terminate");
+    let pauseButton = document.querySelector("#pause");
+    let stats = document.querySelector("#stats");
     let fileInput = document.querySelector("#input-file");
 
     function loadImage() {
@@ -29,6 +31,7 @@ init().then(() => {
     }
 
     let worker;
+    let paused = false;
 
     runButton.addEventListener("click", () => {
         const ctx = target.getContext("2d");
@@ -44,18 +47,41 @@ init().then(() => {
                 case "SVG":
                     svgContainer.innerHTML = e.data[1];
                     break;
+                case "STROKES": {
+                    const svg = svgContainer.querySelector("svg");
+                    if (svg) svg.insertAdjacentHTML("beforeend", e.data.slice(1).join("\n"));
+                    break;
+                }
+                case "STATS":
+                    stats.textContent = `${e.data[1].strokes} strokes, fitness ${e.data[1].fitness.toFixed(2)}, stage ${e.data[1].stage}, ${e.data[1].elapsedSecs.toFixed(1)}s`;
+                    break;
                 case "DONE":
+                    stats.textContent += ` (${e.data[1]})`;
+                    break;
+                case "ERROR":
+                    console.error(e.data[1]);
                     break;
                 default:
                     console.error("Unknown message type");
             }
         };
 
-        worker.postMessage({ pxdata: data, width: target.width, height: target.height });
+        paused = false;
+        pauseButton.value = "Pause";
+        worker.postMessage({ type: "start", pxdata: data, width: target.width, height: target.height }, [data.buffer]);
+    });
+
+    pauseButton.addEventListener("click", () => {
+        if (!worker) return;
+        paused = !paused;
+        pauseButton.value = paused ? "Resume" : "Pause";
+        worker.postMessage({ type: paused ? "pause" : "resume" });
     });
 
     terminateButton.addEventListener("click", () => {
+        if (!worker) return;
         worker.terminate();
+        worker = undefined;
     });
 
     fileInput.addEventListener("change", loadImage);
@@ -1,8 +1,111 @@
-import init, { run_js } from "../pkg/svg_painter.js";
+import init, { Painter } from "../pkg/svg_painter.js";
+
+const STEPS_PER_TICK = 5;
+// Only new strokes are posted, a full SVG is sent every this many strokes to resync the page.
+const SNAPSHOT_INTERVAL = 100;
+// Applied unless the page sends its own, so a run finishes and reports DONE.
+const DEFAULT_STOP = { max_strokes: 5000, max_iterations_without_improvement: 1000 };
+
+let painter;
+let paused = false;
+let ticking = false;
+let sentStrokes = 0;
+let lastSnapshot = 0;
+
+function postSvg() {
+    postMessage(["SVG", painter.svg()]);
+    sentStrokes = painter.stroke_count();
+    lastSnapshot = sentStrokes;
+}
+
+function postNewStrokes(strokes) {
+    if (strokes - lastSnapshot >= SNAPSHOT_INTERVAL) {
+        postSvg();
+        return;
+    }
+    postMessage(["STROKES", ...painter.strokes_since(sentStrokes)]);
+    sentStrokes = strokes;
+}
+
+// Posts the current stats and returns the stroke count and the stop reason, if the run has finished.
+function postStats() {
+    const stats = painter.stats();
+    const stopReason = stats.stop_reason;
+    const strokes = stats.strokes;
+    postMessage(["STATS", {
+        strokes: stats.strokes,
+        fitness: stats.fitness,
+        stage: stats.stage,
+        elapsedSecs: stats.elapsed_secs,
+        seed: stats.seed.toString(),
+    }]);
+    stats.free();
+    return { stopReason, strokes };
+}
+
+function configJson(config) {
+    return JSON.stringify({ ...config, stop: { ...DEFAULT_STOP, ...config?.stop } });
+}
+
+function tick() {
+    ticking = false;
+    if (!painter || paused) return;
+
+    const inserted = painter.step(STEPS_PER_TICK);
+    const { stopReason, strokes } = postStats();
+    if (inserted > 0) {
+        postNewStrokes(strokes);
+    }
+    if (stopReason !== undefined) {
+        postMessage(["DONE", stopReason]);
+        return;
+    }
+    schedule();
+}
+
+function schedule() {
+    if (ticking) return;
+    ticking = true;
+    // Yield to the event loop between ticks, so pause and config messages are handled.
+    setTimeout(tick, 0);
+}
+
+function handle(data) {
+    switch (data.type) {
+        case "start":
+            painter = new Painter(data.pxdata, data.width, data.height, configJson(data.config));
+            paused = false;
+            postSvg();
+            schedule();
+            break;
+        case "pause":
+            paused = true;
+            break;
+        case "resume":
+            paused = false;
+            schedule();
+            break;
+        case "config":
+            painter.set_config(configJson(data.config));
+            schedule();
+            break;
+        case "reset":
+            painter.reset();
+            postSvg();
+            schedule();
+            break;
+        default:
+            postMessage(["ERROR", `Unknown message type ${data.type}`]);
+    }
+}
+
+const ready = init();
 onmessage = (e) => {
-    init().then(() => {
-        const data = e.data;
-        run_js(data.pxdata.join(","), data.width, data.height);
-        postMessage(["DONE"]);
+    ready.then(() => {
+        try {
+            handle(e.data);
+        } catch (err) {
+            postMessage(["ERROR", err.message ?? String(err)]);
+        }
     });
 };
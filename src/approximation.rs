@@ -1,18 +1,19 @@
 use std::fs::File;
 use std::io::Write;
-
-use rand::Rng;
+use std::sync::{Arc, Mutex};
 
 use tiny_skia;
-use tiny_skia::PixmapPaint;
-use tiny_skia::Transform;
 
+use crate::Error;
 use crate::util;
 
 use crate::Controller;
+use crate::brush::BrushLibrary;
 use crate::color::Rgba;
-use crate::stroke;
-use crate::stroke::Stroke;
+use crate::config::{AlphaFormat, ColorEstimator};
+use crate::fitness::{self, FitnessMetric, RenderView};
+use crate::primitive::{self, Primitive};
+use crate::search::{self, SearchStrategy};
 
 pub enum FileType {
     SVG,
@@ -21,135 +22,430 @@ pub enum FileType {
 
 pub struct ImageApproximation {
     target: tiny_skia::Pixmap,
-    strokes: Vec<Stroke>,
+    // Strokes and any other primitives, in painting order.
+    strokes: Vec<Box<dyn Primitive>>,
     pixmap_render: tiny_skia::Pixmap,
     fitness: f64,
+    pixel_errors: Vec<u32>,
+    error_sum: u64,
+    background: Option<Rgba>,
+    // Weight of the error of every pixel, see fitness::distance.
+    importance: Option<Vec<u8>>,
+    metric: Box<dyn FitnessMetric>,
+    alpha_format: AlphaFormat,
+    brushes: Arc<BrushLibrary>,
+    // Full-size copies of the render that candidates are drawn into, so that they are rasterized exactly like the
+    // render itself. An evaluation borrows one and restores the region it painted before returning it.
+    scratch: Mutex<Vec<tiny_skia::Pixmap>>,
+}
+
+// A stroke rendered onto the current render, restricted to the stroke's bounding box.
+#[derive(Clone)]
+pub struct StrokeRender {
+    origin: (u32, u32),
+    patch: tiny_skia::Pixmap,
+    error_sum: u64,
+}
+
+// An evaluated primitive together with its render.
+pub type Candidate = (Box<dyn Primitive>, StrokeRender);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorSolving {
+    pub estimator: ColorEstimator,
+    pub solve_alpha: bool,
+    pub alpha_range: (u8, u8),
+}
+
+impl StrokeRender {
+
+    pub fn get_error_sum(&self) -> u64 {
+        self.error_sum
+    }
+
+    pub fn fitness(&self) -> f64 {
+        (self.error_sum as f64).sqrt()
+    }
+
+    // Number of pixels of the patch.
+    pub fn area(&self) -> u32 {
+        self.patch.width() * self.patch.height()
+    }
 }
 
 impl ImageApproximation {
 
     pub fn new(target: tiny_skia::Pixmap) -> Self {
         let (width, height) = (target.width(), target.height());
-        Self {
-            target: target,
+        let pixmap_render = tiny_skia::Pixmap::new(width, height).unwrap();
+        let mut approx = Self {
+            target,
             strokes: Vec::new(),
-            pixmap_render: tiny_skia::Pixmap::new(width, height).unwrap(),
-            fitness: f64::MAX,
+            pixmap_render,
+            fitness: 0.0,
+            pixel_errors: vec![0; (width * height) as usize],
+            error_sum: 0,
+            background: None,
+            importance: None,
+            metric: Box::new(fitness::RgbL2),
+            alpha_format: AlphaFormat::default(),
+            brushes: BrushLibrary::builtin(),
+            scratch: Mutex::new(Vec::new()),
+        };
+        approx.recompute_errors();
+        approx
+    }
+
+    // Fills the canvas below all strokes with an opaque color. Only meant to be called before any stroke is added.
+    pub fn set_background(&mut self, color: Rgba) {
+        let color = Rgba { a: 255, ..color };
+        self.pixmap_render.fill(tiny_skia::Color::from_rgba8(color.r, color.g, color.b, 255));
+        self.scratch.get_mut().unwrap().clear();
+        self.background = Some(color);
+        self.recompute_errors();
+    }
+
+    // Weights the error of every pixel, so that important regions are painted in more detail.
+    // The weights are given row by row and have to cover the whole image.
+    pub fn set_importance(&mut self, importance: Option<Vec<u8>>) -> Result<(), Error> {
+        if let Some(weights) = &importance {
+            fitness::check_importance(weights, &self.target)?;
+        }
+        self.importance = importance;
+        self.recompute_errors();
+        Ok(())
+    }
+
+    pub fn get_importance(&self) -> Option<&[u8]> {
+        self.importance.as_deref()
+    }
+
+    pub fn set_metric(&mut self, metric: Box<dyn FitnessMetric>) {
+        self.metric = metric;
+        self.recompute_errors();
+    }
+
+    pub fn get_metric(&self) -> &dyn FitnessMetric {
+        self.metric.as_ref()
+    }
+
+    fn recompute_errors(&mut self) {
+        let width = self.target.width();
+        let view = RenderView::new(&self.pixmap_render);
+        self.pixel_errors = (0..self.pixel_errors.len())
+            .map(|idx| self.pixel_error(&view, idx as u32 % width, idx as u32 / width))
+            .collect();
+        self.error_sum = self.pixel_errors.iter().map(|e| *e as u64).sum();
+        self.fitness = (self.error_sum as f64).sqrt();
+    }
+
+    fn pixel_error(&self, render: &RenderView, x: u32, y: u32) -> u32 {
+        let error = self.metric.pixel_error(&self.target, render, x, y);
+        let idx = (y * self.target.width() + x) as usize;
+        fitness::weighted_error(error, self.importance.as_ref().map_or(255, |w| w[idx]))
+    }
+
+    // Pixels whose error changes when the pixels of the region change.
+    fn affected_region(&self, rect: &tiny_skia::IntRect) -> tiny_skia::IntRect {
+        let r = self.metric.radius() as i32;
+        let (width, height) = (self.target.width() as i32, self.target.height() as i32);
+        let (x0, y0) = ((rect.x() - r).max(0), (rect.y() - r).max(0));
+        let (x1, y1) = ((rect.right() + r).min(width), (rect.bottom() + r).min(height));
+        tiny_skia::IntRect::from_xywh(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32).expect("Regions within the image are valid")
+    }
+
+    pub fn set_alpha_format(&mut self, alpha_format: AlphaFormat) {
+        self.alpha_format = alpha_format;
+    }
+
+    // Brushes of the strokes. Fails once strokes were added, as the SVG only defines the brushes of one library.
+    pub fn set_brushes(&mut self, brushes: Arc<BrushLibrary>) -> Result<(), Error> {
+        if !self.strokes.is_empty() {
+            return Err(Error::InvalidBrushes(format!("brushes can not be replaced after {} strokes were added", self.strokes.len())));
         }
+        self.brushes = brushes;
+        Ok(())
+    }
+
+    pub fn load_brushes(&mut self, path: &std::path::Path) -> Result<(), Error> {
+        self.set_brushes(Arc::new(BrushLibrary::from_path(path)?))
+    }
+
+    pub fn get_brushes(&self) -> &Arc<BrushLibrary> {
+        &self.brushes
+    }
+
+    pub fn average_target_color(&self) -> Rgba {
+        let pixels = self.target.pixels();
+        let sums = pixels.iter().fold((0, 0, 0), |s, p| (s.0 + p.red() as u64, s.1 + p.green() as u64, s.2 + p.blue() as u64));
+        average_rgba(sums, pixels.len() as u64).unwrap_or_else(Rgba::new_black)
     }
 
-    pub fn write_to_file(&self, filetype: &FileType, path: &String) {
+    pub fn write_to_file(&self, filetype: &FileType, path: &String) -> Result<(), Error> {
         let expression = self.express();
         match filetype {
             FileType::SVG => {
-                let mut f = File::create(path).expect("Unable to create SVG file");
-                f.write_all(expression.as_bytes()).expect("Unable to write data to disk");
+                let mut f = File::create(path).map_err(|e| Error::Io { path: path.clone(), source: e })?;
+                f.write_all(expression.as_bytes()).map_err(|e| Error::Io { path: path.clone(), source: e })
             },
             FileType::PNG => {
                 let mut canvas = tiny_skia::Pixmap::new(self.target.width(), self.target.height()).unwrap();
-                util::render_svg_into_pixmap(&expression, &mut canvas);
-                canvas.save_png(path).expect("Unable to create PNG file");
+                util::render_svg_into_pixmap(&expression, &mut canvas)?;
+                canvas.save_png(path).map_err(|e| Error::PngEncode { path: path.clone(), message: e.to_string() })
             },
         }
     }
 
-    pub fn add_stroke(&mut self, controller: &Controller) -> bool {
-        let mut rng = rand::thread_rng();
-
-        let mut top_stroke = Stroke::new();
-        top_stroke.set_xy(controller.get_xy());
-        top_stroke.set_rotation(rng.gen_range(0..360));
-        top_stroke.set_scale(controller.get_scale());
+    // Adds a stroke found with the search strategy of the config.
+    pub fn add_stroke(&mut self, controller: &mut Controller) -> bool {
+        let strategy = search::strategy_for(controller.get_search_config());
+        self.add_stroke_with(strategy.as_ref(), controller)
+    }
 
-        match self.approximate_average_color_in_stroke(&top_stroke) {
-            Some(c) => top_stroke.set_color(c),
-            None => return false,
-        };
+    pub fn add_stroke_with(&mut self, strategy: &dyn SearchStrategy, controller: &mut Controller) -> bool {
+        let shape = controller.pick_shape();
+        let mut start = primitive::new_primitive(shape, &self.brushes, controller);
+        start.set_color(Rgba { a: controller.get_alpha(), ..Rgba::new_black() });
+        let Some(start) = self.evaluate_candidate(start, controller.get_color_solving()) else { return false };
 
-        let mut top_render = self.get_render_with_stroke(&top_stroke);
-        let mut top_fitness = util::pixmap_distance(&top_render, &self.target);
-
-        let mut attempts = 0;
-        while attempts < controller.get_max_attempts() {
-            let mut new_stroke = top_stroke.clone();
-            new_stroke.mutate(controller);
-
-            match self.approximate_average_color_in_stroke(&top_stroke) {
-                Some(c) => top_stroke.set_color(c),
-                None => {
-                    attempts += 1;
-                    continue;
-                },
-            };
-
-            let new_render = self.get_render_with_stroke(&new_stroke);
-            let new_fitness = util::pixmap_distance(&new_render, &self.target);
-
-            if new_fitness < top_fitness {
-                top_stroke = new_stroke;
-                top_render = new_render;
-                top_fitness = new_fitness;
-                attempts = 0;
-            } else {
-                attempts += 1;
-            }
-        }
-        return if top_fitness < self.fitness {
+        let (top_stroke, top_render) = strategy.search(self, start, controller);
+        if top_render.fitness() < self.fitness {
             self.strokes.push(top_stroke);
-            self.pixmap_render = top_render;
-            self.fitness = top_fitness;
+            self.apply_stroke_render(&top_render);
             true
         } else {
             false
-        };
+        }
+    }
+
+    pub fn get_fitness(&self) -> f64 {
+        self.fitness
+    }
+
+    pub fn get_strokes(&self) -> &[Box<dyn Primitive>] {
+        &self.strokes
+    }
+
+    pub fn get_render(&self) -> &tiny_skia::Pixmap {
+        &self.pixmap_render
+    }
+
+    pub fn stroke_count(&self) -> usize {
+        self.strokes.len()
     }
 
     pub fn express(&self) -> String {
-        let expressed: String = self.strokes.iter().map(|b| b.express()).collect::<Vec<String>>().join("\n");
+        let mut expressed = self.strokes.iter().map(|b| b.express_with(self.alpha_format)).collect::<Vec<String>>();
+        if let Some(color) = &self.background {
+            expressed.insert(0, format!("<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", color.as_hex()));
+        }
+        let expressed = expressed.join("\n");
         let (width, height) = (self.target.width(), self.target.height());
-        let defs = stroke::STROKES.join("\n");
+        let defs = self.brushes.definitions();
         format!("<svg width=\"{width}\" height=\"{height}\" xmlns=\"http://www.w3.org/2000/svg\">\n<def>\n{defs}\n</def>\n{expressed}\n</svg>")
     }
 
-    pub fn express_stroke(&self, stroke: &Stroke) -> String {
-        let expressed = stroke.express();
+    // The <g> elements of all strokes added after the first `start` ones, to be appended to a previously expressed SVG.
+    pub fn express_strokes_since(&self, start: usize) -> Vec<String> {
+        self.strokes.iter().skip(start).map(|s| s.express_with(self.alpha_format)).collect()
+    }
+
+    pub fn express_stroke(&self, stroke: &dyn Primitive) -> String {
+        let expressed = stroke.express_with(self.alpha_format);
         let (width, height) = (self.target.width(), self.target.height());
-        let defs = stroke::STROKES.join("\n");
+        let defs = self.brushes.definitions();
         format!("<svg width=\"{width}\" height=\"{height}\" xmlns=\"http://www.w3.org/2000/svg\">\n<def>\n{defs}\n</def>\n{expressed}\n</svg>")
     }
 
-    pub fn get_render_with_stroke(&mut self, stroke: &Stroke) -> tiny_skia::Pixmap {
-        let mut stroke_render = tiny_skia::Pixmap::new(self.target.width(), self.target.height()).unwrap();
-        util::render_svg_into_pixmap(&self.express_stroke(&stroke), &mut stroke_render);
-
+    pub fn get_render_with_stroke(&self, stroke: &dyn Primitive) -> tiny_skia::Pixmap {
         let mut render = self.pixmap_render.clone();
-        render.draw_pixmap(0, 0, stroke_render.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
-        return render;
+        stroke.render(&mut render);
+        render
+    }
+
+    // The region of the image a stroke can touch, or None if it lies completely outside.
+    pub fn stroke_region(&self, stroke: &dyn Primitive) -> Option<tiny_skia::IntRect> {
+        let (width, height) = (self.target.width() as i32, self.target.height() as i32);
+        let bounds = stroke.bounding_box()?;
+        // Anti-aliasing may touch the pixels right next to the geometric bounds.
+        let x0 = std::cmp::max(bounds.left().floor() as i32 - 1, 0);
+        let y0 = std::cmp::max(bounds.top().floor() as i32 - 1, 0);
+        let x1 = std::cmp::min(bounds.right().ceil() as i32 + 1, width);
+        let y1 = std::cmp::min(bounds.bottom().ceil() as i32 + 1, height);
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        tiny_skia::IntRect::from_xywh(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32)
+    }
+
+    // Renders the stroke with the same transform as the full render, so its score equals a full recompute.
+    pub fn render_stroke_in_bounds(&self, stroke: &dyn Primitive) -> Option<StrokeRender> {
+        let width = self.target.width();
+        let rect = self.stroke_region(stroke)?;
+        let mut scratch = self.scratch.lock().unwrap().pop().unwrap_or_else(|| self.pixmap_render.clone());
+        stroke.render(&mut scratch);
+
+        let view = RenderView::new(&scratch);
+        let region = self.affected_region(&rect);
+        let mut error_sum = self.error_sum;
+        for y in region.y() as u32..region.bottom() as u32 {
+            for x in region.x() as u32..region.right() as u32 {
+                error_sum -= self.pixel_errors[(y * width + x) as usize] as u64;
+                error_sum += self.pixel_error(&view, x, y) as u64;
+            }
+        }
+        let patch = scratch.clone_rect(rect).expect("Stroke regions lie within the image");
+
+        util::image::copy_rect(&self.pixmap_render, &mut scratch, &rect);
+        self.scratch.lock().unwrap().push(scratch);
+
+        Some(StrokeRender {
+            origin: (rect.x() as u32, rect.y() as u32),
+            patch,
+            error_sum,
+        })
+    }
+
+    // Colors the candidate after the target within its own footprint and renders it.
+    pub fn evaluate_candidate(&self, mut stroke: Box<dyn Primitive>, solving: ColorSolving) -> Option<Candidate> {
+        stroke.set_color(self.estimate_color(stroke.as_ref(), solving)?);
+        let render = self.render_stroke_in_bounds(stroke.as_ref())?;
+        Some((stroke, render))
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn evaluate_candidates(&self, candidates: Vec<Box<dyn Primitive>>, solving: ColorSolving) -> Vec<Option<Candidate>> {
+        use rayon::prelude::*;
+        candidates.into_par_iter().map(|s| self.evaluate_candidate(s, solving)).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    pub fn evaluate_candidates(&self, candidates: Vec<Box<dyn Primitive>>, solving: ColorSolving) -> Vec<Option<Candidate>> {
+        candidates.into_iter().map(|s| self.evaluate_candidate(s, solving)).collect()
     }
 
-    pub fn average_color_in_stroke(&self, stroke: &Stroke) -> Option<Rgba> {
-        let mut mask = tiny_skia::Pixmap::new(self.target.width(), self.target.height()).unwrap();
-        util::render_svg_into_pixmap(&self.express_stroke(&stroke), &mut mask);
+    fn apply_stroke_render(&mut self, stroke_render: &StrokeRender) {
+        let width = self.target.width();
+        let patch = &stroke_render.patch;
+        let (x0, y0) = stroke_render.origin;
+        let render_pixels = self.pixmap_render.pixels_mut();
+        for (i, pixel) in patch.pixels().iter().enumerate() {
+            let (x, y) = (x0 + i as u32 % patch.width(), y0 + i as u32 / patch.width());
+            render_pixels[(y * width + x) as usize] = *pixel;
+        }
+
+        let rect = tiny_skia::IntRect::from_xywh(x0 as i32, y0 as i32, patch.width(), patch.height()).expect("Patches are never empty");
+        for scratch in self.scratch.get_mut().unwrap().iter_mut() {
+            util::image::copy_rect(&self.pixmap_render, scratch, &rect);
+        }
 
+        let region = self.affected_region(&rect);
+        let view = RenderView::new(&self.pixmap_render);
+        let errors = (region.y() as u32..region.bottom() as u32)
+            .flat_map(|y| (region.x() as u32..region.right() as u32).map(move |x| (x, y)))
+            .map(|(x, y)| ((y * width + x) as usize, self.pixel_error(&view, x, y)))
+            .collect::<Vec<(usize, u32)>>();
+        for (idx, error) in errors {
+            self.pixel_errors[idx] = error;
+        }
+        self.error_sum = stroke_render.error_sum;
+        self.fitness = stroke_render.fitness();
+    }
+
+    pub fn estimate_color(&self, stroke: &dyn Primitive, solving: ColorSolving) -> Option<Rgba> {
+        match solving.estimator {
+            ColorEstimator::BoundingSquare => self.approximate_average_color_in_stroke(stroke).map(|c| Rgba { a: stroke.get_color().a, ..c }),
+            ColorEstimator::Coverage => self.average_color_in_stroke(stroke).map(|c| Rgba { a: stroke.get_color().a, ..c }),
+            ColorEstimator::LeastSquares => self.solve_color_in_stroke(stroke, solving.solve_alpha, solving.alpha_range),
+        }
+    }
+
+    // Least squares color for compositing the stroke over the current render. A pixel with coverage m becomes
+    // render + m*a*(color - render), which is linear in the color for a fixed alpha a. When alpha is solved as well,
+    // the optimal color is substituted as a function of a, leaving a one dimensional least squares problem in a.
+    pub fn solve_color_in_stroke(&self, stroke: &dyn Primitive, solve_alpha: bool, alpha_range: (u8, u8)) -> Option<Rgba> {
+        let rect = self.stroke_region(stroke)?;
+        let mask = stroke.coverage_mask(&rect)?;
+
+        let width = self.target.width() as usize;
+        let (x0, y0) = (rect.x() as usize, rect.y() as usize);
+        let mask_width = mask.width() as usize;
+        let target_pixels = self.target.pixels();
+        let render_pixels = self.pixmap_render.pixels();
+
+        let channels = |p: &tiny_skia::PremultipliedColorU8| [p.red() as f64, p.green() as f64, p.blue() as f64];
+        let mut opaque_background = true;
+        let samples = mask.data().iter().enumerate()
+            .filter(|(_, coverage)| **coverage > 0)
+            .filter_map(|(i, coverage)| {
+                let idx = (y0 + i / mask_width) * width + x0 + i % mask_width;
+                if render_pixels[idx].alpha() < 255 {
+                    opaque_background = false;
+                    // Partially covered unpainted pixels stay translucent and count as maximal error whatever the color.
+                    if *coverage < 255 {
+                        return None;
+                    }
+                }
+                Some((*coverage as f64 / 255.0, channels(&render_pixels[idx]), channels(&target_pixels[idx])))
+            })
+            .collect::<Vec<(f64, [f64; 3], [f64; 3])>>();
+        if samples.is_empty() {
+            return None;
+        }
+
+        // A translucent stroke over unpainted pixels leaves them translucent, which never matches the target.
+        let (min_alpha, max_alpha) = (alpha_range.0 as f64 / 255.0, alpha_range.1 as f64 / 255.0);
+        let alpha = if solve_alpha && !opaque_background {
+            max_alpha
+        } else if solve_alpha {
+            (optimal_alpha(&samples) * 255.0).round().clamp(min_alpha * 255.0, max_alpha * 255.0) / 255.0
+        } else {
+            stroke.get_color().a as f64 / 255.0
+        };
+        if alpha <= 0.0 {
+            return None;
+        }
+
+        let mut numerator = [0.0; 3];
+        let mut denominator = 0.0;
+        for (m, render, target) in &samples {
+            let w = m * alpha;
+            for k in 0..3 {
+                numerator[k] += w * (w * render[k] - render[k] + target[k]);
+            }
+            denominator += w * w;
+        }
+        let c = numerator.map(|n| (n / denominator).round().clamp(0.0, 255.0) as u8);
+        Some(Rgba::new(c[0], c[1], c[2], (alpha * 255.0).round() as u8))
+    }
+
+    // Exact average of the target under the stroke, weighted by the anti-aliased coverage of every pixel.
+    pub fn average_color_in_stroke(&self, stroke: &dyn Primitive) -> Option<Rgba> {
+        let rect = self.stroke_region(stroke)?;
+        let mask = stroke.coverage_mask(&rect)?;
+
+        let width = self.target.width() as usize;
+        let (x0, y0) = (rect.x() as usize, rect.y() as usize);
+        let mask_width = mask.width() as usize;
         let target_pixels = self.target.pixels();
 
         let mut colors = (0, 0, 0);
         let mut c = 0;
-        let mut idx = 0;
-        for pixel in mask.pixels() {
-            if pixel.alpha() > 0 {
-                let target_pixel = target_pixels[idx];
-                colors.0 += target_pixel.red() as u64;
-                colors.1 += target_pixel.green() as u64;
-                colors.2 += target_pixel.blue() as u64;
-                c += 1;
+        for (i, coverage) in mask.data().iter().enumerate() {
+            if *coverage == 0 {
+                continue;
             }
-            idx += 1;
+            let weight = *coverage as u64;
+            let target_pixel = target_pixels[(y0 + i / mask_width) * width + x0 + i % mask_width];
+            colors.0 += target_pixel.red() as u64 * weight;
+            colors.1 += target_pixel.green() as u64 * weight;
+            colors.2 += target_pixel.blue() as u64 * weight;
+            c += weight;
         }
-        if c > 0 { Some(Rgba::new((colors.0/c) as u8, (colors.1/c) as u8, (colors.2/c) as u8, 255)) } else { None }
+        average_rgba(colors, c)
     }
 
-    pub fn approximate_average_color_in_stroke(&self, stroke: &Stroke) -> Option<Rgba> {
+    pub fn approximate_average_color_in_stroke(&self, stroke: &dyn Primitive) -> Option<Rgba> {
         let (target_width, target_height) = (self.target.width() as i32, self.target.height() as i32);
 
         let mut colors = (0, 0, 0);
@@ -165,10 +461,53 @@ impl ImageApproximation {
             c += 1;
         }
 
-        if c > 0 { Some(Rgba::new((colors.0/c) as u8, (colors.1/c) as u8, (colors.2/c) as u8, 255)) } else { None }
+        average_rgba(colors, c)
     }
 
     pub fn target_approximation_diffmap(&self) -> tiny_skia::Pixmap {
-        util::image::abs_diff_in_graylevel(&self.target, &self.pixmap_render)
+        util::image::abs_diff_in_graylevel(&self.target, &self.pixmap_render).expect("Render and target always share their dimensions")
+    }
+}
+
+// Least squares alpha, with the color of every channel already substituted by its optimum for the given alpha.
+// Since the error is convex in alpha, clamping to the range that keeps the colors valid gives the constrained optimum.
+fn optimal_alpha(samples: &[(f64, [f64; 3], [f64; 3])]) -> f64 {
+    let mut sum_m2 = 0.0;
+    let mut sum_m2_render = [0.0; 3];
+    let mut sum_m_diff = [0.0; 3];
+    for (m, render, target) in samples {
+        sum_m2 += m * m;
+        for k in 0..3 {
+            sum_m2_render[k] += m * m * render[k];
+            sum_m_diff[k] += m * (render[k] - target[k]);
+        }
+    }
+    let a = sum_m2_render.map(|s| s / sum_m2);
+    let q = sum_m_diff.map(|s| s / sum_m2);
+
+    let mut numerator = 0.0;
+    let mut denominator = 0.0;
+    for (m, render, target) in samples {
+        for k in 0..3 {
+            let e = render[k] - target[k] - m * q[k];
+            let f = m * (a[k] - render[k]);
+            numerator += e * f;
+            denominator += f * f;
+        }
+    }
+    // The optimal color of a channel is a - q/alpha, so small alphas would need colors outside of the valid range.
+    let min_alpha = (0..3).map(|k| match q[k] {
+        q if q > 0.0 => q / a[k],
+        q if q < 0.0 => -q / (255.0 - a[k]),
+        _ => 0.0,
+    }).fold(0.0, f64::max);
+    let alpha = if denominator > 0.0 { -numerator / denominator } else { 1.0 };
+    if alpha.is_nan() { 1.0 } else { alpha.max(min_alpha).min(1.0) }
+}
+
+fn average_rgba(sums: (u64, u64, u64), count: u64) -> Option<Rgba> {
+    if count == 0 {
+        return None;
     }
+    Some(Rgba::new((sums.0/count) as u8, (sums.1/count) as u8, (sums.2/count) as u8, 255))
 }
@@ -0,0 +1,113 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use tiny_skia;
+
+use crate::Error;
+use crate::stroke::{self, STROKE_DIMENSION};
+use crate::util;
+
+static BUILTIN: OnceLock<Arc<BrushLibrary>> = OnceLock::new();
+
+// The brush shapes strokes are drawn with. Every brush fits into STROKE_DIMENSION and is centered on the origin.
+#[derive(Debug)]
+pub struct BrushLibrary {
+    paths: Vec<tiny_skia::Path>,
+    definitions: Vec<String>,
+}
+
+impl BrushLibrary {
+
+    // The brushes of stroke::STROKES, which are already centered and sized to STROKE_DIMENSION.
+    pub fn builtin() -> Arc<Self> {
+        BUILTIN.get_or_init(|| Arc::new(Self {
+            paths: stroke::stroke_paths().to_vec(),
+            definitions: stroke::STROKES.iter().map(|s| s.to_string()).collect(),
+        })).clone()
+    }
+
+    // Normalizes every path to the common brush size. Returns None if no path has an extent.
+    pub fn new(paths: Vec<tiny_skia::Path>) -> Option<Self> {
+        let paths = paths.into_iter().filter_map(normalize).collect::<Vec<tiny_skia::Path>>();
+        if paths.is_empty() {
+            return None;
+        }
+        let definitions = paths.iter().enumerate()
+            .map(|(i, path)| format!("<path id=\"stroke-{i}\" d=\"{}\" />", util::path_data(path)))
+            .collect();
+        Some(Self { paths, definitions })
+    }
+
+    // Every path of the SVG becomes a brush, with the transforms of its parents applied.
+    pub fn from_svg(svg_data: &str) -> Result<Self, Error> {
+        Self::new(paths_of_svg(svg_data)?).ok_or_else(|| Error::InvalidBrushes(String::from("the SVG contains no paths with an area")))
+    }
+
+    // Loads a single SVG file, or all SVG files of a directory in the order of their names.
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        let io_error = |e| Error::Io { path: path.display().to_string(), source: e };
+        let files = if path.is_dir() {
+            let mut files = fs::read_dir(path).map_err(io_error)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>().map_err(io_error)?;
+            files.retain(|f| f.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg")));
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        let mut paths = Vec::new();
+        for file in &files {
+            let svg_data = fs::read_to_string(file).map_err(|e| Error::Io { path: file.display().to_string(), source: e })?;
+            paths.extend(paths_of_svg(&svg_data)?);
+        }
+        Self::new(paths).ok_or_else(|| Error::InvalidBrushes(format!("no SVG paths with an area found in {}", path.display())))
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn get_path(&self, idx: usize) -> &tiny_skia::Path {
+        &self.paths[idx]
+    }
+
+    // <path> elements to place in the <def> section of the SVG, referenced as #stroke-<index>.
+    pub fn definitions(&self) -> String {
+        self.definitions.join("\n")
+    }
+}
+
+fn paths_of_svg(svg_data: &str) -> Result<Vec<tiny_skia::Path>, Error> {
+    let tree = usvg::Tree::from_str(svg_data, &usvg::Options::default(), &fontdb::Database::new())?;
+    let mut paths = Vec::new();
+    collect_paths(tree.root(), &mut paths);
+    Ok(paths)
+}
+
+fn collect_paths(group: &usvg::Group, paths: &mut Vec<tiny_skia::Path>) {
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(group) => collect_paths(group, paths),
+            usvg::Node::Path(path) => paths.extend(path.data().clone().transform(path.abs_transform())),
+            _ => {},
+        }
+    }
+}
+
+// Scales the path uniformly to fit STROKE_DIMENSION and centers it on the origin.
+fn normalize(path: tiny_skia::Path) -> Option<tiny_skia::Path> {
+    let bounds = path.bounds();
+    if bounds.width() <= 0.0 || bounds.height() <= 0.0 {
+        return None;
+    }
+    let scale = (STROKE_DIMENSION.0 / bounds.width()).min(STROKE_DIMENSION.1 / bounds.height());
+    let (cx, cy) = (bounds.left() + bounds.width() / 2.0, bounds.top() + bounds.height() / 2.0);
+    path.transform(tiny_skia::Transform::from_scale(scale, scale).pre_translate(-cx, -cy))
+}
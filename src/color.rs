@@ -1,6 +1,7 @@
 use rand::Rng;
 use crate::util;
 
+#[derive(Debug, PartialEq, Eq)]
 pub struct Rgba {
     pub r: u8,
     pub g: u8,
@@ -10,11 +11,10 @@ pub struct Rgba {
 
 impl Rgba {
     pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
-        Self { r: r, g: g, b: b, a: a }
+        Self { r, g, b, a }
     }
 
-    pub fn new_rand() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn new_rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Rgba::new(rng.gen_range(0..255), rng.gen_range(0..255), rng.gen_range(0..255), rng.gen_range(0..255))
     }
 
@@ -23,11 +23,18 @@ impl Rgba {
     }
 
     pub fn as_hex(&self) -> String {
-        return format!("#{:0>2X}{:0>2X}{:0>2X}", self.r, self.g, self.b);
+        format!("#{:0>2X}{:0>2X}{:0>2X}", self.r, self.g, self.b)
     }
 
-    pub fn mutate(&mut self, magnitude: f64) {
-        let mut rng = rand::thread_rng();
+    pub fn as_hex_with_alpha(&self) -> String {
+        format!("#{:0>2X}{:0>2X}{:0>2X}{:0>2X}", self.r, self.g, self.b, self.a)
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.a as f32 / 255.0
+    }
+
+    pub fn mutate<R: Rng + ?Sized>(&mut self, magnitude: f64, rng: &mut R) {
         let dir_vec: Vec<f64> = (0..4).map(|_| rng.gen_range(-1.0..=1.0)).collect();
         let len = dir_vec.iter().map(|i| i.powf(2.0)).sum::<f64>().sqrt();
         let normed_and_scaled = dir_vec.iter().map(|i| (i/len)*magnitude).collect::<Vec<f64>>();
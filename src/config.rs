@@ -0,0 +1,369 @@
+use std::fmt;
+use std::fs;
+use std::ops::RangeBounds;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::termination::StopConditions;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PainterConfig {
+    pub max_attempts: u32,
+    // Mutated candidates evaluated per attempt. With the `parallel` feature they are evaluated across threads.
+    pub batch_size: u32,
+    pub failed_insertions_per_stage: u32,
+    pub scale_divisor: f32,
+    pub color_estimator: ColorEstimator,
+    // Error measure of the fitness. The scale of the fitness, and so of target_fitness, depends on it.
+    pub metric: MetricKind,
+    // Side length of the window of the SSIM metric.
+    pub ssim_window: u32,
+    // Lets the least squares estimator also choose the stroke's opacity.
+    pub solve_alpha: bool,
+    // Range of the opacity of new strokes. Solved alphas are clamped to it as well.
+    pub min_alpha: u8,
+    pub max_alpha: u8,
+    pub alpha_format: AlphaFormat,
+    // Starts from the average color of the target instead of a transparent canvas, so translucent strokes can blend with it.
+    pub background: bool,
+    pub shapes: ShapeWeights,
+    // Number of vertices of new polygons.
+    pub polygon_vertices: u32,
+    pub placement: PlacementWeights,
+    pub search: SearchConfig,
+    pub mutation: MutationConfig,
+    pub stop: StopConditions,
+    // Seed of the random number generator. Runs with the same seed, target and config produce the same SVG.
+    pub seed: Option<u64>,
+}
+
+impl Default for PainterConfig {
+
+    fn default() -> Self {
+        Self {
+            max_attempts: 25,
+            batch_size: 1,
+            failed_insertions_per_stage: 20,
+            scale_divisor: 8.0,
+            color_estimator: ColorEstimator::default(),
+            metric: MetricKind::default(),
+            ssim_window: 7,
+            solve_alpha: false,
+            min_alpha: 255,
+            max_alpha: 255,
+            alpha_format: AlphaFormat::default(),
+            background: false,
+            shapes: ShapeWeights::default(),
+            polygon_vertices: 5,
+            placement: PlacementWeights::default(),
+            search: SearchConfig::default(),
+            mutation: MutationConfig::default(),
+            stop: StopConditions::default(),
+            seed: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorEstimator {
+    // Plain average over an axis-aligned square around the stroke center, ignoring rotation and shape.
+    BoundingSquare,
+    // Average weighted by the anti-aliased coverage of the transformed stroke path.
+    #[default]
+    Coverage,
+    // Color, and optionally alpha, that minimizes the error after compositing the stroke onto the current render.
+    LeastSquares,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    // Euclidean distance of the sRGB bytes.
+    #[default]
+    RgbL2,
+    // Color channels weighted by their share of the luminance.
+    LuminanceWeighted,
+    // Perceptual color differences in CIELAB.
+    #[serde(rename = "delta_e76")]
+    DeltaE76,
+    #[serde(rename = "delta_e2000")]
+    DeltaE2000,
+    // Structural similarity of the luminance in a window around every pixel.
+    Ssim,
+}
+
+// How the opacity of translucent strokes is written to the SVG.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlphaFormat {
+    #[default]
+    FillOpacity,
+    // #RRGGBBAA colors, shorter but not understood by every SVG consumer.
+    Hex,
+}
+
+// Relative probabilities of the primitive shapes of new strokes. A run may mix any of them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapeWeights {
+    pub stroke: f32,
+    pub triangle: f32,
+    pub rectangle: f32,
+    pub ellipse: f32,
+    pub circle: f32,
+    pub line: f32,
+    pub polygon: f32,
+}
+
+impl Default for ShapeWeights {
+
+    fn default() -> Self {
+        Self {
+            stroke: 1.0,
+            triangle: 0.0,
+            rectangle: 0.0,
+            ellipse: 0.0,
+            circle: 0.0,
+            line: 0.0,
+            polygon: 0.0,
+        }
+    }
+}
+
+impl ShapeWeights {
+
+    pub fn as_array(&self) -> [f32; 7] {
+        [self.stroke, self.triangle, self.rectangle, self.ellipse, self.circle, self.line, self.polygon]
+    }
+}
+
+// Relative share of new strokes placed after each map of the remaining error.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlacementWeights {
+    // Error between target and approximation.
+    pub error: f64,
+    // Error smoothed with a gaussian blur, favoring large erroneous areas over single pixels.
+    pub blurred_error: f64,
+    // Error weighted by the edge strength of the target, favoring details.
+    pub edge_error: f64,
+    pub uniform: f64,
+}
+
+impl Default for PlacementWeights {
+
+    fn default() -> Self {
+        Self {
+            error: 1.0,
+            blurred_error: 0.0,
+            edge_error: 0.0,
+            uniform: 0.0,
+        }
+    }
+}
+
+// How the mutations of a new stroke are searched.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    pub strategy: SearchKind,
+    // Temperatures of simulated annealing, in squared color distance per pixel of the stroke.
+    pub initial_temperature: f64,
+    pub final_temperature: f64,
+    pub schedule: TemperatureSchedule,
+    // Mutation rounds of simulated annealing per stroke. Hill climbing uses max_attempts instead.
+    pub iterations: u32,
+    // Candidate strokes kept by the genetic search, and the number of generations they are evolved for.
+    pub population_size: u32,
+    pub generations: u32,
+    // Candidates competing for each parent slot.
+    pub tournament_size: u32,
+    // Best candidates carried over unchanged into the next generation, at least one.
+    pub elitism: u32,
+    // Probabilities that a child is bred from two parents and that it is mutated afterwards. Parents of different
+    // shapes cannot be crossed over, their children are copies of the first parent.
+    pub crossover_rate: f64,
+    pub mutation_rate: f64,
+}
+
+impl Default for SearchConfig {
+
+    fn default() -> Self {
+        Self {
+            strategy: SearchKind::default(),
+            initial_temperature: 100.0,
+            final_temperature: 0.1,
+            schedule: TemperatureSchedule::default(),
+            iterations: 200,
+            population_size: 16,
+            generations: 20,
+            tournament_size: 3,
+            elitism: 2,
+            crossover_rate: 0.7,
+            mutation_rate: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    // Only accepts improvements.
+    #[default]
+    HillClimbing,
+    // Sometimes accepts worse candidates, less often as the temperature falls.
+    SimulatedAnnealing,
+    // Evolves a population of candidates with crossover and tournament selection.
+    Genetic,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureSchedule {
+    #[default]
+    Exponential,
+    Linear,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MutationConfig {
+    // Maximum translation per mutation, relative to the current stroke size.
+    pub movement_factor: f32,
+    // Maximum rotation per mutation in degrees, applied in both directions.
+    pub rotation_range: i32,
+    // Bounds of the stroke scale per axis, relative to the stroke size of the current stage.
+    pub min_scale: f32,
+    pub max_scale: f32,
+    // Maximum relative change of the scale per axis and mutation.
+    pub scale_step: f32,
+    // Maximum skew angle in degrees and the maximum change per mutation.
+    pub skew_range: f32,
+    pub skew_step: f32,
+    pub weights: MutationWeights,
+}
+
+impl Default for MutationConfig {
+
+    fn default() -> Self {
+        Self {
+            movement_factor: 1.0,
+            rotation_range: 90,
+            min_scale: 0.5,
+            max_scale: 2.0,
+            scale_step: 0.25,
+            skew_range: 20.0,
+            skew_step: 5.0,
+            weights: MutationWeights::default(),
+        }
+    }
+}
+
+// Relative probabilities of the mutation operators. An operator with weight 0 is never applied.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MutationWeights {
+    pub translate: f32,
+    pub rotate: f32,
+    pub scale: f32,
+    pub template: f32,
+    pub skew: f32,
+}
+
+impl Default for MutationWeights {
+
+    fn default() -> Self {
+        Self {
+            translate: 1.0,
+            rotate: 1.0,
+            scale: 1.0,
+            template: 0.25,
+            skew: 0.25,
+        }
+    }
+}
+
+impl MutationWeights {
+
+    pub fn as_array(&self) -> [f32; 5] {
+        [self.translate, self.rotate, self.scale, self.template, self.skew]
+    }
+}
+
+impl PainterConfig {
+
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).map_err(|e| Error::Io { path: path.display().to_string(), source: e })?;
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&content),
+            Some("json") => Self::from_json(&content),
+            _ => Err(Error::Config(format!("unsupported config file {}, expected a .toml or .json file", path.display()))),
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(content: &str) -> Result<Self, Error> {
+        let config: Self = toml::from_str(content).map_err(|e| Error::Config(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, Error> {
+        let config: Self = serde_json::from_str(content).map_err(|e| Error::Config(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Rejects values that parse but would make a run panic or never progress.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(self.scale_divisor.is_finite() && self.scale_divisor > 0.0) {
+            return Err(Error::Config(format!("scale_divisor must be positive, got {}", self.scale_divisor)));
+        }
+        let mutation = &self.mutation;
+        if !(mutation.min_scale.is_finite() && mutation.min_scale > 0.0 && mutation.max_scale.is_finite() && mutation.max_scale > 0.0) {
+            return Err(Error::Config(format!("mutation scale bounds must be positive, got {} and {}", mutation.min_scale, mutation.max_scale)));
+        }
+        if mutation.min_scale > mutation.max_scale {
+            return Err(Error::Config(format!("mutation min_scale {} is greater than max_scale {}", mutation.min_scale, mutation.max_scale)));
+        }
+        check_range("mutation movement_factor", mutation.movement_factor as f64, 0.0..)?;
+        check_range("mutation scale_step", mutation.scale_step as f64, 0.0..1.0)?;
+        check_range("mutation skew_range", mutation.skew_range as f64, 0.0..90.0)?;
+        check_range("mutation skew_step", mutation.skew_step as f64, 0.0..90.0)?;
+        if self.ssim_window == 0 {
+            return Err(Error::Config(String::from("ssim_window must be at least 1")));
+        }
+        let search = &self.search;
+        if search.population_size == 0 {
+            return Err(Error::Config(String::from("search population_size must be at least 1")));
+        }
+        if !(search.initial_temperature.is_finite() && search.final_temperature.is_finite()) {
+            return Err(Error::Config(format!("search temperatures must be finite, got {} and {}", search.initial_temperature, search.final_temperature)));
+        }
+        check_range("search crossover_rate", search.crossover_rate, 0.0..=1.0)?;
+        check_range("search mutation_rate", search.mutation_rate, 0.0..=1.0)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Config should always be serializable")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Config should always be serializable")
+    }
+}
+
+fn check_range(name: &str, value: f64, range: impl RangeBounds<f64> + fmt::Debug) -> Result<(), Error> {
+    if value.is_finite() && range.contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::Config(format!("{name} must be in {range:?}, got {value}")))
+    }
+}
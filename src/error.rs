@@ -0,0 +1,52 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    Io { path: String, source: io::Error },
+    ImageDecode { path: String, message: String },
+    UnsupportedImage { path: String, message: String },
+    PngEncode { path: String, message: String },
+    SvgParse(usvg::Error),
+    Config(String),
+    InvalidDimensions { width: u32, height: u32 },
+    DimensionMismatch { left: (u32, u32), right: (u32, u32) },
+    InvalidPixelData(String),
+    InvalidBrushes(String),
+}
+
+impl fmt::Display for Error {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "I/O error on {path}: {source}"),
+            Error::ImageDecode { path, message } => write!(f, "Failed to decode image {path}: {message}"),
+            Error::UnsupportedImage { path, message } => write!(f, "Unsupported image {path}: {message}"),
+            Error::PngEncode { path, message } => write!(f, "Failed to encode PNG {path}: {message}"),
+            Error::SvgParse(e) => write!(f, "Failed to parse SVG: {e}"),
+            Error::Config(message) => write!(f, "Invalid config: {message}"),
+            Error::InvalidDimensions { width, height } => write!(f, "Invalid image dimensions {width}x{height}"),
+            Error::DimensionMismatch { left, right } => write!(f, "Images of different dimensions can not be compared. Got {}x{} and {}x{}.", left.0, left.1, right.0, right.1),
+            Error::InvalidPixelData(message) => write!(f, "Invalid pixel data: {message}"),
+            Error::InvalidBrushes(message) => write!(f, "Invalid brushes: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::SvgParse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<usvg::Error> for Error {
+
+    fn from(e: usvg::Error) -> Self {
+        Error::SvgParse(e)
+    }
+}
@@ -0,0 +1,283 @@
+use std::sync::OnceLock;
+
+use tiny_skia;
+
+use crate::Error;
+use crate::config::{MetricKind, PainterConfig};
+use crate::util;
+
+// Error of single pixels between target and render. Errors are integral so that sums over regions can be updated
+// incrementally without drift, and the fitness is the square root of their sum.
+pub trait FitnessMetric: Send + Sync {
+
+    fn pixel_error(&self, target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32) -> u32;
+
+    // Distance up to which a changed pixel affects the errors of its neighbors.
+    fn radius(&self) -> u32 {
+        0
+    }
+}
+
+// The render that the errors of the pixels are measured on.
+pub struct RenderView<'a> {
+    render: &'a tiny_skia::Pixmap,
+}
+
+impl<'a> RenderView<'a> {
+
+    pub fn new(render: &'a tiny_skia::Pixmap) -> Self {
+        Self { render }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> tiny_skia::PremultipliedColorU8 {
+        self.render.pixels()[(y * self.render.width() + x) as usize]
+    }
+}
+
+// Squared euclidean distance of the sRGB bytes. Any alpha mismatch counts as the maximum distance.
+pub struct RgbL2;
+
+// Squared differences of the color channels weighted by their share of the luminance.
+pub struct LuminanceWeighted;
+
+// Squared CIE 1976 color difference in CIELAB.
+pub struct DeltaE76;
+
+// Squared CIEDE2000 color difference in CIELAB, which corrects ΔE76 for the perceived uniformity of hue and chroma.
+pub struct DeltaE2000;
+
+// Dissimilarity of the luminance structure in a square window around every pixel.
+pub struct Ssim {
+    radius: u32,
+}
+
+impl Ssim {
+
+    // The window is 2 * radius + 1 pixels wide.
+    pub fn new(radius: u32) -> Self {
+        Self { radius }
+    }
+}
+
+pub fn metric_for(config: &PainterConfig) -> Box<dyn FitnessMetric> {
+    match config.metric {
+        MetricKind::RgbL2 => Box::new(RgbL2),
+        MetricKind::LuminanceWeighted => Box::new(LuminanceWeighted),
+        MetricKind::DeltaE76 => Box::new(DeltaE76),
+        MetricKind::DeltaE2000 => Box::new(DeltaE2000),
+        MetricKind::Ssim => Box::new(Ssim::new(config.ssim_window / 2)),
+    }
+}
+
+// Distance of two images under the given metric, with the error of every pixel weighted by its importance
+// from 0 (ignored) to 255 (full weight).
+pub fn distance(metric: &dyn FitnessMetric, render: &tiny_skia::Pixmap, target: &tiny_skia::Pixmap, importance: Option<&[u8]>) -> Result<f64, Error> {
+    util::check_same_dimensions(render, target)?;
+    if let Some(weights) = importance {
+        check_importance(weights, target)?;
+    }
+    let pixels = target.pixels().len();
+    let view = RenderView::new(render);
+    let width = target.width();
+    let sum = (0..pixels).map(|i| {
+        let error = metric.pixel_error(target, &view, i as u32 % width, i as u32 / width);
+        weighted_error(error, importance.map_or(255, |w| w[i])) as u64
+    }).sum::<u64>();
+    Ok((sum as f64).sqrt())
+}
+
+// Importance weights need one entry per pixel of the target.
+pub fn check_importance(weights: &[u8], target: &tiny_skia::Pixmap) -> Result<(), Error> {
+    let pixels = target.pixels().len();
+    if weights.len() != pixels {
+        return Err(Error::InvalidPixelData(format!("expected {pixels} importance weights, got {}", weights.len())));
+    }
+    Ok(())
+}
+
+pub fn weighted_error(error: u32, weight: u8) -> u32 {
+    (error as u64 * weight as u64 / 255) as u32
+}
+
+fn pixels_at(target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32) -> (tiny_skia::PremultipliedColorU8, tiny_skia::PremultipliedColorU8) {
+    (target.pixels()[(y * target.width() + x) as usize], render.pixel(x, y))
+}
+
+// The sRGB bytes of a pixel composited over black and over white. Comparing both penalizes partial transparency
+// gradually, and a transparent pixel never matches an opaque one.
+fn over_black_and_white(c: &tiny_skia::PremultipliedColorU8) -> [[u8; 3]; 2] {
+    let white = 255 - c.alpha();
+    [
+        [c.red(), c.green(), c.blue()],
+        [c.red() + white, c.green() + white, c.blue() + white],
+    ]
+}
+
+impl FitnessMetric for RgbL2 {
+
+    fn pixel_error(&self, target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32) -> u32 {
+        let (t, r) = pixels_at(target, render, x, y);
+        util::coloru8_distance_sq(&t, &r)
+    }
+}
+
+impl FitnessMetric for LuminanceWeighted {
+
+    // Weighted by three times the luminance coefficients, so a difference of the same size in all channels costs as much as in RgbL2.
+    fn pixel_error(&self, target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32) -> u32 {
+        let (t, r) = pixels_at(target, render, x, y);
+        let error = over_black_and_white(&t).iter().zip(over_black_and_white(&r).iter()).map(|(t, r)| {
+            let d = [t[0] as f64 - r[0] as f64, t[1] as f64 - r[1] as f64, t[2] as f64 - r[2] as f64];
+            3.0 * (0.299 * d[0] * d[0] + 0.587 * d[1] * d[1] + 0.114 * d[2] * d[2])
+        }).sum::<f64>() / 2.0;
+        error.round() as u32
+    }
+}
+
+// Squared color differences are scaled by this factor before rounding, so that differences below one ΔE still count.
+const DELTA_E_SCALE: f64 = 16.0;
+
+fn delta_e_error(target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32, delta_e: fn([f64; 3], [f64; 3]) -> f64) -> u32 {
+    let (t, r) = pixels_at(target, render, x, y);
+    if t == r {
+        return 0;
+    }
+    let error = over_black_and_white(&t).iter().zip(over_black_and_white(&r).iter())
+        .map(|(t, r)| delta_e(srgb_to_lab(*t), srgb_to_lab(*r)).powi(2))
+        .sum::<f64>() / 2.0;
+    (error * DELTA_E_SCALE).round() as u32
+}
+
+impl FitnessMetric for DeltaE76 {
+
+    fn pixel_error(&self, target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32) -> u32 {
+        delta_e_error(target, render, x, y, delta_e76)
+    }
+}
+
+impl FitnessMetric for DeltaE2000 {
+
+    fn pixel_error(&self, target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32) -> u32 {
+        delta_e_error(target, render, x, y, delta_e2000)
+    }
+}
+
+impl FitnessMetric for Ssim {
+
+    // (1 - SSIM) / 2 ranges from 0 to 1 and is scaled to the maximum error of RgbL2.
+    fn pixel_error(&self, target: &tiny_skia::Pixmap, render: &RenderView, x: u32, y: u32) -> u32 {
+        const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+        const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+        let r = self.radius;
+        let (x0, y0) = (x.saturating_sub(r), y.saturating_sub(r));
+        let (x1, y1) = ((x + r).min(target.width() - 1), (y + r).min(target.height() - 1));
+        let n = ((x1 - x0 + 1) * (y1 - y0 + 1)) as f64;
+
+        // Sums of the target and render luminance over black and over white, their squares and their products.
+        let mut sums = [[0.0; 5]; 2];
+        for j in y0..=y1 {
+            for i in x0..=x1 {
+                let (t, r) = pixels_at(target, render, i, j);
+                let (t, r) = (over_black_and_white(&t), over_black_and_white(&r));
+                for (s, (t, r)) in sums.iter_mut().zip(t.iter().zip(r.iter())) {
+                    let (lt, lr) = (luminance(*t), luminance(*r));
+                    s[0] += lt;
+                    s[1] += lr;
+                    s[2] += lt * lt;
+                    s[3] += lr * lr;
+                    s[4] += lt * lr;
+                }
+            }
+        }
+
+        let dissimilarity = sums.iter().map(|s| {
+            let (mean_t, mean_r) = (s[0] / n, s[1] / n);
+            let var_t = (s[2] / n - mean_t * mean_t).max(0.0);
+            let var_r = (s[3] / n - mean_r * mean_r).max(0.0);
+            let covariance = s[4] / n - mean_t * mean_r;
+            let ssim = ((2.0 * mean_t * mean_r + C1) * (2.0 * covariance + C2))
+                / ((mean_t * mean_t + mean_r * mean_r + C1) * (var_t + var_r + C2));
+            (1.0 - ssim) / 2.0
+        }).sum::<f64>() / 2.0;
+        (dissimilarity * (255.0 * 255.0 * 3.0)).round() as u32
+    }
+
+    fn radius(&self) -> u32 {
+        self.radius
+    }
+}
+
+fn luminance(c: [u8; 3]) -> f64 {
+    0.299 * c[0] as f64 + 0.587 * c[1] as f64 + 0.114 * c[2] as f64
+}
+
+static SRGB_TO_LINEAR: OnceLock<[f64; 256]> = OnceLock::new();
+
+// CIELAB coordinates of an sRGB color under the D65 white point.
+pub fn srgb_to_lab(c: [u8; 3]) -> [f64; 3] {
+    let linear = SRGB_TO_LINEAR.get_or_init(|| std::array::from_fn(|i| {
+        let v = i as f64 / 255.0;
+        if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+    }));
+    let (r, g, b) = (linear[c[0] as usize], linear[c[1] as usize], linear[c[2] as usize]);
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+
+    let f = |t: f64| if t > 216.0 / 24389.0 { t.cbrt() } else { (24389.0 / 27.0 * t + 16.0) / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+pub fn delta_e76(lab1: [f64; 3], lab2: [f64; 3]) -> f64 {
+    ((lab1[0] - lab2[0]).powi(2) + (lab1[1] - lab2[1]).powi(2) + (lab1[2] - lab2[2]).powi(2)).sqrt()
+}
+
+// CIEDE2000 as given by Sharma, Wu and Dalal, with all weighting factors set to 1.
+pub fn delta_e2000(lab1: [f64; 3], lab2: [f64; 3]) -> f64 {
+    let [l1, a1, b1] = lab1;
+    let [l2, a2, b2] = lab2;
+    let pow7 = |v: f64| v.powi(7);
+
+    let c_bar = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5 * (1.0 - (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt());
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |b: f64, a: f64| if a == 0.0 && b == 0.0 { 0.0 } else { b.atan2(a).to_degrees().rem_euclid(360.0) };
+    let (h1, h2) = (hue(b1, a1), hue(b2, a2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else {
+        let d = h2 - h1;
+        if d > 180.0 { d - 360.0 } else if d < -180.0 { d + 360.0 } else { d }
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_bar = (l1 + l2) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t = 1.0 - 0.17 * cos(h_bar - 30.0) + 0.24 * cos(2.0 * h_bar) + 0.32 * cos(3.0 * h_bar + 6.0) - 0.20 * cos(4.0 * h_bar - 63.0);
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt();
+    let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let (dl, dc, dh) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (dl * dl + dc * dc + dh * dh + r_t * dc * dh).sqrt()
+}
@@ -2,12 +2,33 @@ use wasm_bindgen::prelude::*;
 
 use std::cmp;
 
+use rand::SeedableRng;
+
+pub mod brush;
+
 pub mod color;
 
+pub mod config;
+use config::PainterConfig;
+
+pub mod error;
+pub use error::Error;
+
 pub mod approximation;
+pub mod fitness;
 use approximation::*;
 
+pub mod painter;
+pub mod placement;
+use painter::*;
+
+pub mod primitive;
+pub mod search;
+
 pub mod stroke;
+pub mod termination;
+use termination::*;
+
 pub mod util;
 
 #[wasm_bindgen]
@@ -16,74 +37,205 @@ extern {
 }
 
 #[wasm_bindgen]
-pub fn run_js(pixmap_data_str: &str, width: u32, height: u32) {
-    let mut pixmap = tiny_skia::Pixmap::new(width, height).unwrap();
-    let data = pixmap.data_mut();
-    let mut idx = 0;
-    for s in pixmap_data_str.split(",").collect::<Vec<&str>>() {
-        data[idx] = s.parse::<u8>().expect("Could not parse pixel data.");
-        idx += 1;
-    }
-    run(&pixmap, |img_approx: &ImageApproximation| {
-        postMessage(vec![String::from("SVG"), img_approx.express()]);
+pub fn run_js(pixels: &[u8], width: u32, height: u32, config_json: Option<String>, seed: Option<u32>, snapshot_interval: Option<u32>, importance: Option<Vec<u8>>) -> Result<String, JsError> {
+    let pixmap = pixmap_from_canvas_data(pixels, width, height)?;
+    let mut config = match config_json {
+        Some(json) => PainterConfig::from_json(&json)?,
+        None => PainterConfig::default(),
+    };
+    if let Some(seed) = seed {
+        config.seed = Some(seed as u64);
+    }
+    let mut painter = Painter::new(pixmap, &config);
+    if let Some(importance) = importance {
+        painter.set_importance(&pixmap_from_canvas_data(&importance, width, height)?)?;
+    }
+    // Without a snapshot interval the full SVG is posted after every insertion. Otherwise only the new
+    // strokes are posted, with a full SVG every `snapshot_interval` strokes to resync the receiver.
+    let mut sent_strokes = 0;
+    let (_, reason) = run_painter(painter, |img_approx: &ImageApproximation| {
+        let strokes = img_approx.stroke_count();
+        match snapshot_interval {
+            Some(n) if n > 0 && strokes > 0 && strokes % n as usize != 0 => {
+                let mut message = vec![String::from("STROKES")];
+                message.extend(img_approx.express_strokes_since(sent_strokes));
+                postMessage(message);
+            },
+            _ => postMessage(vec![String::from("SVG"), img_approx.express()]),
+        }
+        sent_strokes = strokes;
     });
+    Ok(String::from(reason.code()))
 }
 
-pub fn run<F>(target: &tiny_skia::Pixmap, hook_successful_insertion: F) where F: Fn(&ImageApproximation) {
-    let mut mask = tiny_skia::Pixmap::new(target.width(), target.height()).unwrap();
-    mask.fill(tiny_skia::Color::WHITE);
+// Canvas image data is straight RGBA, row by row, while pixmaps are premultiplied.
+pub fn pixmap_from_canvas_data(pixels: &[u8], width: u32, height: u32) -> Result<tiny_skia::Pixmap, Error> {
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidDimensions { width, height });
+    }
+    let expected = width as usize * height as usize * 4;
+    if pixels.len() != expected {
+        return Err(Error::InvalidPixelData(format!("expected {expected} bytes for {width}x{height} pixels, got {}", pixels.len())));
+    }
+    util::pixmap_from_rgba(width, height, pixels).ok_or(Error::InvalidDimensions { width, height })
+}
 
-    let mut controller = Controller::new(&mask);
-    controller.set_scale(calc_scale(&target, 1));
+pub fn run<F>(target: &tiny_skia::Pixmap, config: &PainterConfig, hook_successful_insertion: F) -> (ImageApproximation, StopReason) where F: FnMut(&ImageApproximation) {
+    run_painter(Painter::new(target.clone(), config), hook_successful_insertion)
+}
+
+// Like run, for a painter that was set up beyond its config, e.g. with custom brushes.
+pub fn run_painter<F>(painter: Painter, mut hook_successful_insertion: F) -> (ImageApproximation, StopReason) where F: FnMut(&ImageApproximation) {
+    let result = try_run_painter(painter, |img_approx: &ImageApproximation| {
+        hook_successful_insertion(img_approx);
+        Ok::<(), std::convert::Infallible>(())
+    });
+    match result {
+        Ok(finished) => finished,
+        Err(never) => match never {},
+    }
+}
 
-    let mut approx = ImageApproximation::new(target.clone());
-    hook_successful_insertion(&approx);
+// Like run_painter, but stops at the first error of the hook and returns it.
+pub fn try_run_painter<F, E>(mut painter: Painter, mut hook_successful_insertion: F) -> Result<(ImageApproximation, StopReason), E> where F: FnMut(&ImageApproximation) -> Result<(), E> {
+    // The time budget covers the whole run, including the time spent in the hook.
+    let (stopwatch, elapsed_before) = (Stopwatch::start(), painter.get_elapsed());
+    hook_successful_insertion(painter.approximation())?;
 
-    let mut stage = 1;
-    let mut failed_insertions = 0;
     loop {
-        let success = approx.add_stroke(&controller);
-        if success {
-            failed_insertions = 0;
-
-            let new_mask = approx.target_approximation_diffmap();
-            controller.set_mask_from_pixmap(&new_mask);
-
-            hook_successful_insertion(&approx);
-        } else {
-            failed_insertions += 1;
-            if failed_insertions == 20 {
-                failed_insertions = 0;
-                stage += 1;
-                let scale = calc_scale(&target, stage);
-                controller.set_scale(scale);
+        painter.set_elapsed(elapsed_before + stopwatch.elapsed());
+        match painter.step() {
+            Step::Inserted => hook_successful_insertion(painter.approximation())?,
+            Step::Rejected => {},
+            Step::Stopped(reason) => return Ok((painter.into_approximation(), reason)),
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = Painter)]
+pub struct JsPainter {
+    painter: Painter,
+}
+
+#[wasm_bindgen(js_class = Painter)]
+impl JsPainter {
+
+    #[wasm_bindgen(constructor)]
+    pub fn new(pixels: &[u8], width: u32, height: u32, config_json: Option<String>) -> Result<JsPainter, JsError> {
+        let pixmap = pixmap_from_canvas_data(pixels, width, height)?;
+        let config = match config_json {
+            Some(json) => PainterConfig::from_json(&json)?,
+            None => PainterConfig::default(),
+        };
+        Ok(Self { painter: Painter::new(pixmap, &config) })
+    }
+
+    // Makes up to n insertion attempts and returns how many strokes were added.
+    pub fn step(&mut self, n: u32) -> u32 {
+        let mut inserted = 0;
+        for _ in 0..n {
+            match self.painter.step() {
+                Step::Inserted => inserted += 1,
+                Step::Rejected => {},
+                Step::Stopped(_) => break,
             }
         }
+        inserted
+    }
+
+    pub fn svg(&self) -> String {
+        self.painter.approximation().express()
+    }
+
+    pub fn stroke_count(&self) -> usize {
+        self.painter.approximation().stroke_count()
+    }
+
+    pub fn strokes_since(&self, start: usize) -> Vec<String> {
+        self.painter.approximation().express_strokes_since(start)
+    }
+
+    pub fn stats(&self) -> PainterStats {
+        self.painter.stats()
+    }
+
+    pub fn set_config(&mut self, config_json: &str) -> Result<(), JsError> {
+        self.painter.set_config(&PainterConfig::from_json(config_json)?);
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        self.painter.reset();
+    }
+
+    // Grayscale canvas data of the target's size. Brighter regions are painted in finer detail.
+    pub fn set_importance(&mut self, pixels: &[u8]) -> Result<(), JsError> {
+        let render = self.painter.approximation().get_render();
+        let mask = pixmap_from_canvas_data(pixels, render.width(), render.height())?;
+        self.painter.set_importance(&mask)?;
+        Ok(())
+    }
+
+    // Replaces the built-in brushes with every path of the given SVG. Starts the run over if strokes were already added.
+    pub fn set_brushes(&mut self, svg: &str) -> Result<(), JsError> {
+        self.painter.set_brushes(std::sync::Arc::new(brush::BrushLibrary::from_svg(svg)?));
+        Ok(())
     }
 }
 
+pub type PainterRng = rand_chacha::ChaCha8Rng;
+
 pub struct Controller {
     mask: util::image::GraylevelMask,
+    mutation_dist: Option<rand_distr::WeightedIndex<f32>>,
+    shape_dist: Option<rand_distr::WeightedIndex<f32>>,
     scale_x: f32,
     scale_y: f32,
+    config: PainterConfig,
+    seed: u64,
+    rng: PainterRng,
 }
 
 impl Controller {
 
-    pub fn new(src_image: &tiny_skia::Pixmap) -> Self {
+    pub fn new(src_image: &tiny_skia::Pixmap, config: &PainterConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(rand::random);
         Self {
             mask: util::image::GraylevelMask::from(src_image),
+            mutation_dist: weighted_distribution(&config.mutation.weights.as_array()),
+            shape_dist: weighted_distribution(&config.shapes.as_array()),
             scale_x: 1.0,
             scale_y: 1.0,
+            config: config.clone(),
+            seed,
+            rng: PainterRng::seed_from_u64(seed),
         }
     }
 
+    pub fn set_config(&mut self, config: &PainterConfig) {
+        self.config = config.clone();
+        self.mutation_dist = weighted_distribution(&config.mutation.weights.as_array());
+        self.shape_dist = weighted_distribution(&config.shapes.as_array());
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut PainterRng {
+        &mut self.rng
+    }
+
     pub fn set_mask_from_pixmap(&mut self, pixmap: &tiny_skia::Pixmap) {
         self.mask = util::image::GraylevelMask::from(pixmap);
     }
 
-    pub fn get_xy(&self) -> (i32, i32) {
-        let xy = self.mask.sample_random_xy();
+    pub fn set_mask(&mut self, mask: util::image::GraylevelMask) {
+        self.mask = mask;
+    }
+
+    pub fn get_xy(&mut self) -> (i32, i32) {
+        let xy = self.mask.sample_random_xy(&mut self.rng);
         (xy.0 as i32, xy.1 as i32)
     }
 
@@ -98,21 +250,89 @@ impl Controller {
 
     pub fn get_mutation_movement(&self) -> (i32, i32) {
         let scale = self.get_scale();
+        let factor = self.config.mutation.movement_factor;
         (
-            cmp::max((stroke::STROKE_DIMENSION.0 * scale.0) as i32, 1),
-            cmp::max((stroke::STROKE_DIMENSION.1 * scale.1) as i32, 1),
+            cmp::max((stroke::STROKE_DIMENSION.0 * scale.0 * factor) as i32, 1),
+            cmp::max((stroke::STROKE_DIMENSION.1 * scale.1 * factor) as i32, 1),
         )
     }
 
+    pub fn get_mutation_config(&self) -> &config::MutationConfig {
+        &self.config.mutation
+    }
+
+    // Falls back to translations if no mutation operator has a positive weight.
+    pub fn pick_mutation(&mut self) -> stroke::Mutation {
+        match &self.mutation_dist {
+            Some(dist) => stroke::Mutation::ALL[rand_distr::Distribution::sample(dist, &mut self.rng)],
+            None => stroke::Mutation::Translate,
+        }
+    }
+
+    // Falls back to strokes if no shape has a positive weight.
+    pub fn pick_shape(&mut self) -> primitive::Shape {
+        match &self.shape_dist {
+            Some(dist) => primitive::Shape::ALL[rand_distr::Distribution::sample(dist, &mut self.rng)],
+            None => primitive::Shape::Stroke,
+        }
+    }
+
+    pub fn get_polygon_vertices(&self) -> usize {
+        cmp::max(self.config.polygon_vertices, 3) as usize
+    }
+
+    pub fn get_mutation_rotation(&self) -> i32 {
+        cmp::max(self.config.mutation.rotation_range, 1)
+    }
+
+    pub fn get_color_estimator(&self) -> config::ColorEstimator {
+        self.config.color_estimator
+    }
+
+    pub fn get_solve_alpha(&self) -> bool {
+        self.config.solve_alpha
+    }
+
+    pub fn get_alpha_range(&self) -> (u8, u8) {
+        let (min, max) = (self.config.min_alpha.max(1), self.config.max_alpha.max(1));
+        (cmp::min(min, max), cmp::max(min, max))
+    }
+
+    pub fn get_color_solving(&self) -> ColorSolving {
+        ColorSolving {
+            estimator: self.get_color_estimator(),
+            solve_alpha: self.get_solve_alpha(),
+            alpha_range: self.get_alpha_range(),
+        }
+    }
+
+    pub fn get_search_config(&self) -> &config::SearchConfig {
+        &self.config.search
+    }
+
+    pub fn get_alpha(&mut self) -> u8 {
+        let (min, max) = self.get_alpha_range();
+        rand::Rng::gen_range(&mut self.rng, min..=max)
+    }
+
     pub fn get_max_attempts(&self) -> u32 {
-        25
+        self.config.max_attempts
+    }
+
+    pub fn get_batch_size(&self) -> u32 {
+        cmp::max(self.config.batch_size, 1)
     }
 }
 
-fn calc_scale(target: &tiny_skia::Pixmap, stage: u32) -> (f32, f32) {
+fn weighted_distribution(weights: &[f32]) -> Option<rand_distr::WeightedIndex<f32>> {
+    let weights = weights.iter().map(|w| if w.is_finite() { w.max(0.0) } else { 0.0 });
+    rand_distr::WeightedIndex::new(weights).ok()
+}
+
+fn calc_scale(target: &tiny_skia::Pixmap, stage: u32, divisor: f32) -> (f32, f32) {
     let max_dim = cmp::max(target.width(), target.height()) as f32;
     (
-        max_dim/(stroke::STROKE_DIMENSION.0 * 8.0 * stage as f32),
-        max_dim/(stroke::STROKE_DIMENSION.1 * 8.0 * stage as f32),
+        max_dim/(stroke::STROKE_DIMENSION.0 * divisor * stage as f32),
+        max_dim/(stroke::STROKE_DIMENSION.1 * divisor * stage as f32),
     )
 }
\ No newline at end of file
@@ -1,22 +1,183 @@
 use std::fs;
-use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
 
 use svg_painter::approximation::{FileType, ImageApproximation};
 
-use svg_painter::run;
+use svg_painter::{try_run_painter, Error};
+use svg_painter::brush::BrushLibrary;
+use svg_painter::config::PainterConfig;
+use svg_painter::painter::Painter;
+
+// Failed insertions in a row after which a run stops, unless the config file or a flag sets the limit.
+// Without it, a run with only the default config never finishes.
+const DEFAULT_MAX_ITERATIONS_WITHOUT_IMPROVEMENT: u32 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Svg,
+    Png,
+}
+
+impl OutputFormat {
+
+    fn file_type(&self) -> FileType {
+        match self {
+            OutputFormat::Svg => FileType::SVG,
+            OutputFormat::Png => FileType::PNG,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Svg => "svg",
+            OutputFormat::Png => "png",
+        }
+    }
+}
+
+/// Recreate a raster based image as a SVG.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// Image to approximate (PNG, JPEG, WebP, GIF, BMP or TIFF)
+    input: PathBuf,
+
+    /// Output path, the extension is replaced for every output format
+    #[arg(short, long, default_value = "build/expr")]
+    output: PathBuf,
+
+    /// Formats to write
+    #[arg(short, long, value_enum, value_delimiter = ',', default_value = "svg,png")]
+    format: Vec<OutputFormat>,
+
+    /// Write the outputs after every N accepted strokes, 0 only writes the final result
+    #[arg(long, default_value_t = 1, value_name = "N")]
+    snapshot_interval: usize,
+
+    /// Stop after this many strokes
+    #[arg(long, value_name = "N")]
+    max_strokes: Option<usize>,
+
+    /// Stop after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    time_budget: Option<f64>,
 
-static BUILD: &str = "build";
+    /// Stop after this many failed insertions in a row [default: 1000]
+    #[arg(long, value_name = "N")]
+    max_iterations_without_improvement: Option<u32>,
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+    /// Seed of the random number generator, makes runs reproducible
+    #[arg(long)]
+    seed: Option<u64>,
 
-    let raster_image_path = &args[1];
+    /// TOML or JSON file with a PainterConfig
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
 
-    fs::create_dir_all(String::from(BUILD)).expect("Unable to create build directory");
-    fs::copy(raster_image_path, format!("{BUILD}/trgt.png")).expect("Could not copy target file");
+    /// SVG file or directory of SVG files whose paths replace the built-in brushes
+    #[arg(short, long, value_name = "PATH")]
+    brushes: Option<PathBuf>,
+
+    /// Grayscale image of the input's size, brighter regions are painted in finer detail
+    #[arg(short, long, value_name = "FILE")]
+    importance: Option<PathBuf>,
+
+    /// Print progress, repeat for more detail
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Print nothing but errors
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match paint(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn paint(cli: &Cli) -> Result<(), Error> {
+    let mut config = match &cli.config {
+        Some(path) => PainterConfig::from_file(path)?,
+        None => PainterConfig::default(),
+    };
+    if cli.max_strokes.is_some() {
+        config.stop.max_strokes = cli.max_strokes;
+    }
+    if let Some(secs) = cli.time_budget {
+        config.stop.time_budget = Some(Duration::try_from_secs_f64(secs).map_err(|e| Error::Config(format!("invalid time budget {secs}: {e}")))?);
+    }
+    if cli.max_iterations_without_improvement.is_some() {
+        config.stop.max_iterations_without_improvement = cli.max_iterations_without_improvement;
+    }
+    config.stop.max_iterations_without_improvement.get_or_insert(DEFAULT_MAX_ITERATIONS_WITHOUT_IMPROVEMENT);
+    if cli.seed.is_some() {
+        config.seed = cli.seed;
+    }
+    let seed = *config.seed.get_or_insert_with(rand::random);
+
+    let target = svg_painter::util::read_image(&cli.input.to_string_lossy().into_owned())?;
+    let brushes = cli.brushes.as_deref().map(BrushLibrary::from_path).transpose()?;
+    let importance = cli.importance.as_ref().map(|path| svg_painter::util::read_image(&path.to_string_lossy().into_owned())).transpose()?;
+
+    if let Some(dir) = cli.output.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| Error::Io { path: dir.display().to_string(), source: e })?;
+    }
+
+    if cli.verbose > 0 {
+        eprintln!("Approximating {} ({}x{}) with seed {seed}", cli.input.display(), target.width(), target.height());
+        if let Some(brushes) = &brushes {
+            eprintln!("Using {} brushes", brushes.len());
+        }
+        if let Some(path) = &cli.importance {
+            eprintln!("Using importance mask {}", path.display());
+        }
+    }
+    if cli.verbose > 1 {
+        eprintln!("{}", config.to_toml());
+    }
+
+    let mut painter = Painter::new(target, &config);
+    if let Some(brushes) = brushes {
+        painter.set_brushes(Arc::new(brushes));
+    }
+    if let Some(importance) = &importance {
+        painter.set_importance(importance)?;
+    }
+
+    let (approx, reason) = try_run_painter(painter, |img_approx: &ImageApproximation| {
+        let strokes = img_approx.stroke_count();
+        if cli.verbose > 0 && strokes > 0 {
+            eprintln!("stroke {strokes}: fitness {:.2}", img_approx.get_fitness());
+        }
+        if strokes > 0 && cli.snapshot_interval > 0 && strokes % cli.snapshot_interval == 0 {
+            write_outputs(img_approx, &cli.output, &cli.format)?;
+        }
+        Ok::<(), Error>(())
+    })?;
+    write_outputs(&approx, &cli.output, &cli.format)?;
+
+    if !cli.quiet {
+        println!("Finished ({}) with {} strokes, fitness {:.2}, seed {seed}", reason.code(), approx.stroke_count(), approx.get_fitness());
+    }
+    Ok(())
+}
 
-    run(&svg_painter::util::read_image(raster_image_path), |img_approx: &ImageApproximation| {
-        img_approx.write_to_file(&FileType::SVG, &format!("{BUILD}/expr.svg"));
-        img_approx.write_to_file(&FileType::PNG, &format!("{BUILD}/expr.png"));
-    });
+fn write_outputs(approx: &ImageApproximation, output: &Path, formats: &[OutputFormat]) -> Result<(), Error> {
+    for format in formats {
+        let path = output.with_extension(format.extension());
+        approx.write_to_file(&format.file_type(), &path.to_string_lossy().into_owned())?;
+    }
+    Ok(())
 }
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use wasm_bindgen::prelude::*;
+
+use crate::approximation::ImageApproximation;
+use crate::brush::BrushLibrary;
+use crate::calc_scale;
+use crate::config::PainterConfig;
+use crate::fitness;
+use crate::placement::Placement;
+use crate::termination::{Progress, StopReason, Stopwatch};
+use crate::util;
+use crate::{Controller, Error};
+
+pub enum Step {
+    Inserted,
+    Rejected,
+    Stopped(StopReason),
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct PainterStats {
+    pub strokes: usize,
+    pub fitness: f64,
+    pub stage: u32,
+    pub iterations_without_improvement: u32,
+    pub elapsed_secs: f64,
+    pub seed: u64,
+    pub stop_reason: Option<String>,
+}
+
+// Runs the approximation one insertion attempt at a time, so callers can control the pacing.
+pub struct Painter {
+    target: tiny_skia::Pixmap,
+    config: PainterConfig,
+    controller: Controller,
+    placement: Placement,
+    approx: ImageApproximation,
+    stage: u32,
+    failed_insertions: u32,
+    iterations_without_improvement: u32,
+    elapsed: Duration,
+    stop_reason: Option<StopReason>,
+}
+
+impl Painter {
+
+    pub fn new(target: tiny_skia::Pixmap, config: &PainterConfig) -> Self {
+        let mut config = config.clone();
+        config.seed = Some(config.seed.unwrap_or_else(rand::random));
+
+        let mut mask = tiny_skia::Pixmap::new(target.width(), target.height()).unwrap();
+        mask.fill(tiny_skia::Color::WHITE);
+
+        let mut controller = Controller::new(&mask, &config);
+        controller.set_scale(calc_scale(&target, 1, config.scale_divisor));
+
+        let mut approx = ImageApproximation::new(target.clone());
+        approx.set_alpha_format(config.alpha_format);
+        approx.set_metric(fitness::metric_for(&config));
+        if config.background {
+            approx.set_background(approx.average_target_color());
+        }
+
+        let placement = Placement::new(&target, &config.placement);
+        controller.set_mask(placement.mask(&approx));
+
+        Self {
+            approx,
+            target,
+            config,
+            controller,
+            placement,
+            stage: 1,
+            failed_insertions: 0,
+            iterations_without_improvement: 0,
+            elapsed: Duration::ZERO,
+            stop_reason: None,
+        }
+    }
+
+    pub fn step(&mut self) -> Step {
+        if let Some(reason) = self.stop_reason {
+            return Step::Stopped(reason);
+        }
+        if let Some(reason) = self.config.stop.check(&self.progress()) {
+            self.stop_reason = Some(reason);
+            return Step::Stopped(reason);
+        }
+
+        let stopwatch = Stopwatch::start();
+        let success = self.approx.add_stroke(&mut self.controller);
+        if success {
+            self.failed_insertions = 0;
+            self.iterations_without_improvement = 0;
+
+            self.controller.set_mask(self.placement.mask(&self.approx));
+        } else {
+            self.failed_insertions += 1;
+            self.iterations_without_improvement += 1;
+            if self.failed_insertions >= self.config.failed_insertions_per_stage {
+                self.failed_insertions = 0;
+                self.stage += 1;
+                self.controller.set_scale(calc_scale(&self.target, self.stage, self.config.scale_divisor));
+            }
+        }
+        self.elapsed += stopwatch.elapsed();
+
+        if success { Step::Inserted } else { Step::Rejected }
+    }
+
+    pub fn approximation(&self) -> &ImageApproximation {
+        &self.approx
+    }
+
+    pub fn into_approximation(self) -> ImageApproximation {
+        self.approx
+    }
+
+    pub fn get_config(&self) -> &PainterConfig {
+        &self.config
+    }
+
+    // Applies a new config to the running approximation. A finished run may continue if the new stop conditions allow it.
+    pub fn set_config(&mut self, config: &PainterConfig) {
+        let seed = self.config.seed;
+        self.config = config.clone();
+        if self.config.seed.is_none() {
+            self.config.seed = seed;
+        }
+        self.controller.set_config(&self.config);
+        self.approx.set_alpha_format(self.config.alpha_format);
+        self.approx.set_metric(fitness::metric_for(&self.config));
+        self.placement = Placement::new(&self.target, &self.config.placement);
+        self.controller.set_mask(self.placement.mask(&self.approx));
+        self.controller.set_scale(calc_scale(&self.target, self.stage, self.config.scale_divisor));
+        self.stop_reason = None;
+    }
+
+    // Replaces the brushes of the strokes. The SVG only defines the brushes of one library, so a run that
+    // already added strokes starts over.
+    pub fn set_brushes(&mut self, brushes: Arc<BrushLibrary>) {
+        if self.approx.stroke_count() > 0 {
+            self.reset();
+        }
+        self.approx.set_brushes(brushes).expect("No strokes were added since the reset");
+    }
+
+    // Grayscale mask of the target's size. Brighter regions weigh more in the fitness and receive more strokes,
+    // so they are painted in finer detail.
+    pub fn set_importance(&mut self, mask: &tiny_skia::Pixmap) -> Result<(), Error> {
+        util::check_same_dimensions(&self.target, mask)?;
+        self.approx.set_importance(Some(util::image::importance_weights(mask)))?;
+        self.controller.set_mask(self.placement.mask(&self.approx));
+        Ok(())
+    }
+
+    // Starts over from an empty approximation. With the same seed and config the run repeats exactly.
+    pub fn reset(&mut self) {
+        let brushes = self.approx.get_brushes().clone();
+        let importance = self.approx.get_importance().map(|w| w.to_vec());
+        *self = Self::new(self.target.clone(), &self.config);
+        self.approx.set_brushes(brushes).expect("A new approximation has no strokes");
+        if importance.is_some() {
+            self.approx.set_importance(importance).expect("Importance was set for the same target");
+            self.controller.set_mask(self.placement.mask(&self.approx));
+        }
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    // Time counted against the time budget. step only adds its own duration, so time between steps, e.g. while
+    // the web page is paused, does not count. run_painter sets it to the wall-clock time of the run instead.
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            strokes: self.approx.stroke_count(),
+            fitness: self.approx.get_fitness(),
+            stage: self.stage,
+            iterations_without_improvement: self.iterations_without_improvement,
+            elapsed: self.elapsed,
+        }
+    }
+
+    pub fn stats(&self) -> PainterStats {
+        PainterStats {
+            strokes: self.approx.stroke_count(),
+            fitness: self.approx.get_fitness(),
+            stage: self.stage,
+            iterations_without_improvement: self.iterations_without_improvement,
+            elapsed_secs: self.elapsed.as_secs_f64(),
+            seed: self.controller.get_seed(),
+            stop_reason: self.stop_reason.map(|r| String::from(r.code())),
+        }
+    }
+}
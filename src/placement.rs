@@ -0,0 +1,60 @@
+use crate::approximation::ImageApproximation;
+use crate::config::PlacementWeights;
+use crate::util::image::{self, GraylevelMask};
+
+// Builds the mask new strokes are placed with, by mixing several maps of where the approximation falls short.
+// Every map is normalized to sum up to one before mixing, so a weight is the share of samples drawn from that map.
+pub struct Placement {
+    weights: PlacementWeights,
+    // Edge strength of the target, which does not change during a run.
+    edges: Vec<f64>,
+}
+
+impl Placement {
+
+    pub fn new(target: &tiny_skia::Pixmap, weights: &PlacementWeights) -> Self {
+        let edges = if weights.edge_error > 0.0 { image::graylevels(&image::sobel(target)) } else { Vec::new() };
+        Self {
+            weights: weights.clone(),
+            edges,
+        }
+    }
+
+    pub fn mask(&self, approx: &ImageApproximation) -> GraylevelMask {
+        let diffmap = approx.target_approximation_diffmap();
+        let (width, height) = (diffmap.width(), diffmap.height());
+        let error = image::graylevels(&diffmap);
+        let mut mixed = vec![0.0; error.len()];
+
+        if self.weights.error > 0.0 {
+            add_normalized(&mut mixed, &error, self.weights.error);
+        }
+        if self.weights.blurred_error > 0.0 {
+            add_normalized(&mut mixed, &image::graylevels(&image::gaussian_blur(&diffmap)), self.weights.blurred_error);
+        }
+        if self.weights.edge_error > 0.0 {
+            let edge_error = error.iter().zip(&self.edges).map(|(e, g)| e * g / 255.0).collect::<Vec<f64>>();
+            add_normalized(&mut mixed, &edge_error, self.weights.edge_error);
+        }
+        if self.weights.uniform > 0.0 {
+            add_normalized(&mut mixed, &vec![1.0; error.len()], self.weights.uniform);
+        }
+        // Strokes are placed in important regions only as much as their importance allows.
+        if let Some(importance) = approx.get_importance() {
+            for (m, w) in mixed.iter_mut().zip(importance) {
+                *m *= *w as f64 / 255.0;
+            }
+        }
+        GraylevelMask::from_weights(&mixed, width, height)
+    }
+}
+
+fn add_normalized(mixed: &mut [f64], layer: &[f64], weight: f64) {
+    let sum = layer.iter().sum::<f64>();
+    if sum <= 0.0 || !weight.is_finite() {
+        return;
+    }
+    for (m, l) in mixed.iter_mut().zip(layer) {
+        *m += weight * l / sum;
+    }
+}
@@ -0,0 +1,136 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use tiny_skia;
+
+use crate::Controller;
+use crate::brush::BrushLibrary;
+use crate::color::Rgba;
+use crate::config::AlphaFormat;
+use crate::stroke::Stroke;
+
+pub mod shapes;
+use shapes::*;
+
+// Anything the approximation can place, color, mutate and export. Rendering, bounds and footprint follow
+// from the outline and its transform, so a shape only has to describe its geometry.
+pub trait Primitive: Send + Sync {
+
+    // Filled outline of the primitive, in its own coordinates.
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>>;
+
+    // Maps the outline into the image.
+    fn transform(&self) -> tiny_skia::Transform {
+        tiny_skia::Transform::identity()
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String;
+
+    fn mutate(&mut self, controller: &mut Controller);
+
+    fn get_color(&self) -> &Rgba;
+
+    fn set_color(&mut self, color: Rgba);
+
+    fn box_clone(&self) -> Box<dyn Primitive>;
+
+    // Offspring combining the parameters of both parents, used by the genetic search.
+    // Primitives that cannot be combined with the other one return None.
+    fn crossover(&self, _other: &dyn Primitive, _controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        None
+    }
+
+    // Lets crossover find out whether the other parent is of the same shape.
+    fn as_any(&self) -> &dyn Any;
+
+    fn express(&self) -> String {
+        self.express_with(AlphaFormat::default())
+    }
+
+    fn bounding_box(&self) -> Option<tiny_skia::Rect> {
+        self.path()?.bounds().transform(self.transform())
+    }
+
+    fn render(&self, pixmap: &mut tiny_skia::Pixmap) {
+        let Some(path) = self.path() else { return };
+        let transform = self.transform();
+        let color = self.get_color();
+        let mut paint = tiny_skia::Paint::default();
+        paint.set_color_rgba8(color.r, color.g, color.b, color.a);
+        paint.anti_alias = true;
+        pixmap.fill_path(&path, &paint, tiny_skia::FillRule::Winding, transform, None);
+    }
+
+    // Anti-aliased coverage of the primitive within the given region of the image.
+    fn coverage_mask(&self, rect: &tiny_skia::IntRect) -> Option<tiny_skia::Mask> {
+        let path = self.path()?;
+        let mut mask = tiny_skia::Mask::new(rect.width(), rect.height())?;
+        let transform = tiny_skia::Transform::from_translate(-rect.x() as f32, -rect.y() as f32).pre_concat(self.transform());
+        mask.fill_path(&path, tiny_skia::FillRule::Winding, true, transform);
+        Some(mask)
+    }
+
+    // Pixels of the axis-aligned bounding box, used by the rough bounding square color estimator.
+    fn approximate_pixels(&self) -> Vec<(i32, i32)> {
+        let Some(bounds) = self.bounding_box() else { return Vec::new() };
+        let mut pixels = Vec::new();
+        for i in bounds.left().floor() as i32..bounds.right().ceil() as i32 {
+            for j in bounds.top().floor() as i32..bounds.bottom().ceil() as i32 {
+                pixels.push((i, j));
+            }
+        }
+        pixels
+    }
+}
+
+impl Clone for Box<dyn Primitive> {
+
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Stroke,
+    Triangle,
+    Rectangle,
+    Ellipse,
+    Circle,
+    Line,
+    Polygon,
+}
+
+impl Shape {
+
+    pub const ALL: [Shape; 7] = [Shape::Stroke, Shape::Triangle, Shape::Rectangle, Shape::Ellipse, Shape::Circle, Shape::Line, Shape::Polygon];
+}
+
+// A new primitive of the given shape around a sampled position, sized after the stroke size of the current stage.
+pub fn new_primitive(shape: Shape, brushes: &Arc<BrushLibrary>, controller: &mut Controller) -> Box<dyn Primitive> {
+    match shape {
+        Shape::Stroke => {
+            let mut stroke = Stroke::with_brushes(brushes.clone(), controller.rng());
+            stroke.set_xy(controller.get_xy());
+            stroke.set_rotation(rand::Rng::gen_range(controller.rng(), 0..360));
+            stroke.set_scale(controller.get_scale());
+            Box::new(stroke)
+        },
+        Shape::Triangle => Box::new(Triangle::random(controller)),
+        Shape::Rectangle => Box::new(Rectangle::random(controller)),
+        Shape::Ellipse => Box::new(Ellipse::random(controller)),
+        Shape::Circle => Box::new(Circle::random(controller)),
+        Shape::Line => Box::new(QuadraticLine::random(controller)),
+        Shape::Polygon => Box::new(Polygon::random(controller)),
+    }
+}
+
+// Fill or stroke attributes of an SVG element in the given color.
+pub fn paint_attributes(attribute: &str, color: &Rgba, alpha_format: AlphaFormat) -> String {
+    match alpha_format {
+        _ if color.a == 255 => format!("{attribute}=\"{}\"", color.as_hex()),
+        AlphaFormat::FillOpacity => format!("{attribute}=\"{}\" {attribute}-opacity=\"{:.3}\"", color.as_hex(), color.opacity()),
+        AlphaFormat::Hex => format!("{attribute}=\"{}\"", color.as_hex_with_alpha()),
+    }
+}
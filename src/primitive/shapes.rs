@@ -0,0 +1,589 @@
+use std::any::Any;
+use std::borrow::Cow;
+
+use rand::Rng;
+
+use tiny_skia;
+
+use crate::Controller;
+use crate::color::Rgba;
+use crate::config::{AlphaFormat, MutationConfig};
+use crate::primitive::{paint_attributes, Primitive};
+use crate::stroke::{Mutation, STROKE_DIMENSION};
+use crate::util;
+
+// Shapes have no templates or skew, so these operators fall back to the closest change of their geometry.
+// Crossover mixes the geometry of two shapes of the same kind: center, size and rotation of boxed shapes, the
+// vertices of triangles, polygons and lines.
+
+#[derive(Clone)]
+pub struct Triangle {
+    points: [(f32, f32); 3],
+    color: Rgba,
+}
+
+#[derive(Clone)]
+pub struct Polygon {
+    points: Vec<(f32, f32)>,
+    color: Rgba,
+}
+
+#[derive(Clone)]
+pub struct Rectangle {
+    center: (f32, f32),
+    size: (f32, f32),
+    rotation: f32,
+    color: Rgba,
+}
+
+#[derive(Clone)]
+pub struct Ellipse {
+    center: (f32, f32),
+    radii: (f32, f32),
+    rotation: f32,
+    color: Rgba,
+}
+
+#[derive(Clone)]
+pub struct Circle {
+    center: (f32, f32),
+    radius: f32,
+    color: Rgba,
+}
+
+// A quadratic Bézier curve drawn with a round-capped line.
+#[derive(Clone)]
+pub struct QuadraticLine {
+    points: [(f32, f32); 3],
+    width: f32,
+    color: Rgba,
+}
+
+impl Triangle {
+
+    pub fn new(points: [(f32, f32); 3], color: Rgba) -> Self {
+        Self { points, color }
+    }
+
+    pub fn random(controller: &mut Controller) -> Self {
+        let points = random_star(3, controller);
+        Self::new([points[0], points[1], points[2]], Rgba::new_black())
+    }
+
+    pub fn get_points(&self) -> &[(f32, f32); 3] {
+        &self.points
+    }
+}
+
+impl Polygon {
+
+    pub fn new(points: Vec<(f32, f32)>, color: Rgba) -> Self {
+        Self { points, color }
+    }
+
+    pub fn random(controller: &mut Controller) -> Self {
+        let vertices = controller.get_polygon_vertices();
+        Self::new(random_star(vertices, controller), Rgba::new_black())
+    }
+
+    pub fn get_points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+}
+
+impl Rectangle {
+
+    pub fn new(center: (f32, f32), size: (f32, f32), rotation: f32, color: Rgba) -> Self {
+        Self { center, size, rotation, color }
+    }
+
+    pub fn random(controller: &mut Controller) -> Self {
+        let center = random_center(controller);
+        let size = stage_size(controller);
+        let rng = controller.rng();
+        let size = (size.0 * rng.gen_range(0.25..1.0), size.1 * rng.gen_range(0.25..1.0));
+        let rotation = rng.gen_range(0.0..180.0);
+        Self::new(center, size, rotation, Rgba::new_black())
+    }
+}
+
+impl Ellipse {
+
+    pub fn new(center: (f32, f32), radii: (f32, f32), rotation: f32, color: Rgba) -> Self {
+        Self { center, radii, rotation, color }
+    }
+
+    pub fn random(controller: &mut Controller) -> Self {
+        let center = random_center(controller);
+        let size = stage_size(controller);
+        let rng = controller.rng();
+        let radii = (size.0 * rng.gen_range(0.125..0.5), size.1 * rng.gen_range(0.125..0.5));
+        let rotation = rng.gen_range(0.0..180.0);
+        Self::new(center, radii, rotation, Rgba::new_black())
+    }
+}
+
+impl Circle {
+
+    pub fn new(center: (f32, f32), radius: f32, color: Rgba) -> Self {
+        Self { center, radius, color }
+    }
+
+    pub fn random(controller: &mut Controller) -> Self {
+        let center = random_center(controller);
+        let size = stage_size(controller);
+        let radius = size.0.min(size.1) * controller.rng().gen_range(0.125..0.5);
+        Self::new(center, radius, Rgba::new_black())
+    }
+}
+
+impl QuadraticLine {
+
+    pub fn new(points: [(f32, f32); 3], width: f32, color: Rgba) -> Self {
+        Self { points, width, color }
+    }
+
+    pub fn random(controller: &mut Controller) -> Self {
+        let points = random_star(3, controller);
+        let size = stage_size(controller);
+        let width = (size.0.min(size.1) * controller.rng().gen_range(0.05..0.2)).max(1.0);
+        Self::new([points[0], points[1], points[2]], width, Rgba::new_black())
+    }
+}
+
+impl Primitive for Triangle {
+
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>> {
+        polygon_path(&self.points).map(Cow::Owned)
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String {
+        format!("<polygon points=\"{}\" {}/>", express_points(&self.points), paint_attributes("fill", &self.color, alpha_format))
+    }
+
+    fn mutate(&mut self, controller: &mut Controller) {
+        let mutation = controller.pick_mutation();
+        mutate_points(&mut self.points, mutation, controller);
+    }
+
+    fn get_color(&self) -> &Rgba {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Rgba) {
+        self.color = color;
+    }
+
+    fn box_clone(&self) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
+    fn crossover(&self, other: &dyn Primitive, controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        let other = other.as_any().downcast_ref::<Triangle>()?;
+        let mut child = self.clone();
+        let rng = controller.rng();
+        crossover_points(&mut child.points, &other.points, rng);
+        child.color.a = either(self.color.a, other.color.a, rng);
+        Some(Box::new(child))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Primitive for Polygon {
+
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>> {
+        polygon_path(&self.points).map(Cow::Owned)
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String {
+        format!("<polygon points=\"{}\" {}/>", express_points(&self.points), paint_attributes("fill", &self.color, alpha_format))
+    }
+
+    fn mutate(&mut self, controller: &mut Controller) {
+        let mutation = controller.pick_mutation();
+        mutate_points(&mut self.points, mutation, controller);
+    }
+
+    fn get_color(&self) -> &Rgba {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Rgba) {
+        self.color = color;
+    }
+
+    fn box_clone(&self) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
+    fn crossover(&self, other: &dyn Primitive, controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        let other = other.as_any().downcast_ref::<Polygon>()?;
+        if self.points.len() != other.points.len() {
+            return None;
+        }
+        let mut child = self.clone();
+        let rng = controller.rng();
+        crossover_points(&mut child.points, &other.points, rng);
+        child.color.a = either(self.color.a, other.color.a, rng);
+        Some(Box::new(child))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Primitive for Rectangle {
+
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>> {
+        let rect = tiny_skia::Rect::from_xywh(-self.size.0 / 2.0, -self.size.1 / 2.0, self.size.0, self.size.1)?;
+        Some(Cow::Owned(tiny_skia::PathBuilder::from_rect(rect)))
+    }
+
+    fn transform(&self) -> tiny_skia::Transform {
+        tiny_skia::Transform::from_translate(self.center.0, self.center.1).pre_rotate(self.rotation)
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String {
+        format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" transform=\"translate({:.2} {:.2}) rotate({:.2})\" {}/>",
+            -self.size.0 / 2.0, -self.size.1 / 2.0, self.size.0, self.size.1,
+            self.center.0, self.center.1, self.rotation,
+            paint_attributes("fill", &self.color, alpha_format),
+        )
+    }
+
+    fn mutate(&mut self, controller: &mut Controller) {
+        mutate_oriented(&mut self.center, &mut self.size, &mut self.rotation, controller);
+    }
+
+    fn get_color(&self) -> &Rgba {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Rgba) {
+        self.color = color;
+    }
+
+    fn box_clone(&self) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
+    fn crossover(&self, other: &dyn Primitive, controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        let other = other.as_any().downcast_ref::<Rectangle>()?;
+        let mut child = self.clone();
+        let rng = controller.rng();
+        child.center = either(self.center, other.center, rng);
+        child.size = either(self.size, other.size, rng);
+        child.rotation = either(self.rotation, other.rotation, rng);
+        child.color.a = either(self.color.a, other.color.a, rng);
+        Some(Box::new(child))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Primitive for Ellipse {
+
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>> {
+        ellipse_path((0.0, 0.0), self.radii).map(Cow::Owned)
+    }
+
+    fn transform(&self) -> tiny_skia::Transform {
+        tiny_skia::Transform::from_translate(self.center.0, self.center.1).pre_rotate(self.rotation)
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String {
+        format!(
+            "<ellipse rx=\"{:.2}\" ry=\"{:.2}\" transform=\"translate({:.2} {:.2}) rotate({:.2})\" {}/>",
+            self.radii.0, self.radii.1, self.center.0, self.center.1, self.rotation,
+            paint_attributes("fill", &self.color, alpha_format),
+        )
+    }
+
+    fn mutate(&mut self, controller: &mut Controller) {
+        // Radii are half of the extent, so they are bounded like a size halved.
+        let mut size = (self.radii.0 * 2.0, self.radii.1 * 2.0);
+        mutate_oriented(&mut self.center, &mut size, &mut self.rotation, controller);
+        self.radii = (size.0 / 2.0, size.1 / 2.0);
+    }
+
+    fn get_color(&self) -> &Rgba {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Rgba) {
+        self.color = color;
+    }
+
+    fn box_clone(&self) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
+    fn crossover(&self, other: &dyn Primitive, controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        let other = other.as_any().downcast_ref::<Ellipse>()?;
+        let mut child = self.clone();
+        let rng = controller.rng();
+        child.center = either(self.center, other.center, rng);
+        child.radii = either(self.radii, other.radii, rng);
+        child.rotation = either(self.rotation, other.rotation, rng);
+        child.color.a = either(self.color.a, other.color.a, rng);
+        Some(Box::new(child))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Primitive for Circle {
+
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>> {
+        ellipse_path(self.center, (self.radius, self.radius)).map(Cow::Owned)
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String {
+        format!(
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" {}/>",
+            self.center.0, self.center.1, self.radius,
+            paint_attributes("fill", &self.color, alpha_format),
+        )
+    }
+
+    fn mutate(&mut self, controller: &mut Controller) {
+        let movement = controller.get_mutation_movement();
+        let base_size = stage_size(controller);
+        let config = controller.get_mutation_config().clone();
+        let mutation = controller.pick_mutation();
+        let rng = controller.rng();
+        match mutation {
+            Mutation::Translate | Mutation::Rotate => translate_point(&mut self.center, movement, rng),
+            Mutation::Scale | Mutation::Template | Mutation::Skew => {
+                let diameter = scale_extent(self.radius * 2.0, base_size.0.min(base_size.1), &config, rng);
+                self.radius = diameter / 2.0;
+            },
+        }
+    }
+
+    fn get_color(&self) -> &Rgba {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Rgba) {
+        self.color = color;
+    }
+
+    fn box_clone(&self) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
+    fn crossover(&self, other: &dyn Primitive, controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        let other = other.as_any().downcast_ref::<Circle>()?;
+        let mut child = self.clone();
+        let rng = controller.rng();
+        child.center = either(self.center, other.center, rng);
+        child.radius = either(self.radius, other.radius, rng);
+        child.color.a = either(self.color.a, other.color.a, rng);
+        Some(Box::new(child))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Primitive for QuadraticLine {
+
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>> {
+        let mut builder = tiny_skia::PathBuilder::new();
+        builder.move_to(self.points[0].0, self.points[0].1);
+        builder.quad_to(self.points[1].0, self.points[1].1, self.points[2].0, self.points[2].1);
+        let stroke = tiny_skia::Stroke {
+            width: self.width,
+            line_cap: tiny_skia::LineCap::Round,
+            ..tiny_skia::Stroke::default()
+        };
+        builder.finish()?.stroke(&stroke, 1.0).map(Cow::Owned)
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String {
+        let [p0, p1, p2] = self.points;
+        format!(
+            "<path d=\"M {:.2} {:.2} Q {:.2} {:.2} {:.2} {:.2}\" fill=\"none\" {} stroke-width=\"{:.2}\" stroke-linecap=\"round\"/>",
+            p0.0, p0.1, p1.0, p1.1, p2.0, p2.1,
+            paint_attributes("stroke", &self.color, alpha_format), self.width,
+        )
+    }
+
+    // Skews change the line width, all other operators act on the control points.
+    fn mutate(&mut self, controller: &mut Controller) {
+        let mutation = controller.pick_mutation();
+        if mutation == Mutation::Skew {
+            let base_size = stage_size(controller);
+            let step = controller.get_mutation_config().scale_step.abs();
+            let max_width = (base_size.0.min(base_size.1) / 2.0).max(1.0);
+            self.width = (self.width * (1.0 + controller.rng().gen_range(-step..=step))).clamp(1.0, max_width);
+        } else {
+            mutate_points(&mut self.points, mutation, controller);
+        }
+    }
+
+    fn get_color(&self) -> &Rgba {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Rgba) {
+        self.color = color;
+    }
+
+    fn box_clone(&self) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
+    fn crossover(&self, other: &dyn Primitive, controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        let other = other.as_any().downcast_ref::<QuadraticLine>()?;
+        let mut child = self.clone();
+        let rng = controller.rng();
+        crossover_points(&mut child.points, &other.points, rng);
+        child.width = either(self.width, other.width, rng);
+        child.color.a = either(self.color.a, other.color.a, rng);
+        Some(Box::new(child))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Extent of a stroke at the scale of the current stage.
+fn stage_size(controller: &Controller) -> (f32, f32) {
+    let scale = controller.get_scale();
+    (STROKE_DIMENSION.0 * scale.0, STROKE_DIMENSION.1 * scale.1)
+}
+
+fn random_center(controller: &mut Controller) -> (f32, f32) {
+    let xy = controller.get_xy();
+    (xy.0 as f32, xy.1 as f32)
+}
+
+// Vertices at increasing angles around a sampled center, so the outline never intersects itself.
+fn random_star(vertices: usize, controller: &mut Controller) -> Vec<(f32, f32)> {
+    let center = random_center(controller);
+    let size = stage_size(controller);
+    let rng = controller.rng();
+    let mut angles = (0..vertices).map(|_| rng.gen_range(0.0..std::f32::consts::TAU)).collect::<Vec<f32>>();
+    angles.sort_by(f32::total_cmp);
+    angles.into_iter().map(|angle| {
+        let radius = rng.gen_range(0.25..0.5);
+        (center.0 + angle.cos() * size.0 * radius, center.1 + angle.sin() * size.1 * radius)
+    }).collect()
+}
+
+fn polygon_path(points: &[(f32, f32)]) -> Option<tiny_skia::Path> {
+    let mut builder = tiny_skia::PathBuilder::new();
+    let (first, rest) = points.split_first()?;
+    builder.move_to(first.0, first.1);
+    for point in rest {
+        builder.line_to(point.0, point.1);
+    }
+    builder.close();
+    builder.finish()
+}
+
+// Four elliptical arcs, the way SVG renderers build ellipses, so the render matches the exported SVG.
+fn ellipse_path(center: (f32, f32), radii: (f32, f32)) -> Option<tiny_skia::Path> {
+    let ((cx, cy), (rx, ry)) = (center, radii);
+    util::parse_path_data(&format!(
+        "M {} {cy} A {rx} {ry} 0 0 1 {cx} {} A {rx} {ry} 0 0 1 {} {cy} A {rx} {ry} 0 0 1 {cx} {} A {rx} {ry} 0 0 1 {} {cy} Z",
+        cx + rx, cy + ry, cx - rx, cy - ry, cx + rx,
+    ))
+}
+
+fn express_points(points: &[(f32, f32)]) -> String {
+    points.iter().map(|p| format!("{:.2},{:.2}", p.0, p.1)).collect::<Vec<String>>().join(" ")
+}
+
+// Uniform crossover picks every parameter from either parent.
+fn either<T, R: Rng + ?Sized>(own: T, other: T, rng: &mut R) -> T {
+    if rng.gen_bool(0.5) { other } else { own }
+}
+
+fn crossover_points<R: Rng + ?Sized>(points: &mut [(f32, f32)], other: &[(f32, f32)], rng: &mut R) {
+    for (point, other) in points.iter_mut().zip(other) {
+        *point = either(*point, *other, rng);
+    }
+}
+
+fn translate_point<R: Rng + ?Sized>(point: &mut (f32, f32), movement: (i32, i32), rng: &mut R) {
+    point.0 += rng.gen_range(-movement.0..=movement.0) as f32;
+    point.1 += rng.gen_range(-movement.1..=movement.1) as f32;
+}
+
+// Scales an extent by a random step, keeping it within the configured bounds relative to the stage.
+fn scale_extent<R: Rng + ?Sized>(extent: f32, base: f32, config: &MutationConfig, rng: &mut R) -> f32 {
+    let step = config.scale_step.abs();
+    let (min, max) = (config.min_scale.min(config.max_scale), config.max_scale.max(config.min_scale));
+    (extent * (1.0 + rng.gen_range(-step..=step))).clamp(base * min, base * max)
+}
+
+fn mutate_points(points: &mut [(f32, f32)], mutation: Mutation, controller: &mut Controller) {
+    let movement = controller.get_mutation_movement();
+    let rotation = controller.get_mutation_rotation();
+    let base_size = stage_size(controller);
+    let config = controller.get_mutation_config().clone();
+    let rng = controller.rng();
+
+    let n = points.len() as f32;
+    let centroid = points.iter().fold((0.0, 0.0), |c, p| (c.0 + p.0 / n, c.1 + p.1 / n));
+    match mutation {
+        Mutation::Translate => {
+            let mut offset = (0.0, 0.0);
+            translate_point(&mut offset, movement, rng);
+            points.iter_mut().for_each(|p| *p = (p.0 + offset.0, p.1 + offset.1));
+        },
+        Mutation::Rotate => {
+            let (sin, cos) = (rng.gen_range(-rotation..=rotation) as f32).to_radians().sin_cos();
+            points.iter_mut().for_each(|p| {
+                let (dx, dy) = (p.0 - centroid.0, p.1 - centroid.1);
+                *p = (centroid.0 + dx * cos - dy * sin, centroid.1 + dx * sin + dy * cos);
+            });
+        },
+        Mutation::Scale => {
+            let (min, max) = points.iter().fold(((f32::MAX, f32::MAX), (f32::MIN, f32::MIN)), |(min, max), p| {
+                ((min.0.min(p.0), min.1.min(p.1)), (max.0.max(p.0), max.1.max(p.1)))
+            });
+            let extent = ((max.0 - min.0).max(1.0), (max.1 - min.1).max(1.0));
+            let factor = (
+                scale_extent(extent.0, base_size.0, &config, rng) / extent.0,
+                scale_extent(extent.1, base_size.1, &config, rng) / extent.1,
+            );
+            points.iter_mut().for_each(|p| *p = (centroid.0 + (p.0 - centroid.0) * factor.0, centroid.1 + (p.1 - centroid.1) * factor.1));
+        },
+        Mutation::Template | Mutation::Skew => {
+            let i = rng.gen_range(0..points.len());
+            translate_point(&mut points[i], movement, rng);
+        },
+    }
+}
+
+fn mutate_oriented(center: &mut (f32, f32), size: &mut (f32, f32), rotation: &mut f32, controller: &mut Controller) {
+    let movement = controller.get_mutation_movement();
+    let rotation_range = controller.get_mutation_rotation() as f32;
+    let base_size = stage_size(controller);
+    let config = controller.get_mutation_config().clone();
+    let mutation = controller.pick_mutation();
+    let rng = controller.rng();
+    match mutation {
+        Mutation::Translate => translate_point(center, movement, rng),
+        Mutation::Rotate | Mutation::Skew => *rotation = (*rotation + rng.gen_range(-rotation_range..=rotation_range)) % 360.0,
+        Mutation::Scale | Mutation::Template => {
+            size.0 = scale_extent(size.0, base_size.0, &config, rng);
+            size.1 = scale_extent(size.1, base_size.1, &config, rng);
+        },
+    }
+}
@@ -0,0 +1,207 @@
+use rand::Rng;
+
+use crate::Controller;
+use crate::approximation::{Candidate, ImageApproximation};
+use crate::config::{SearchConfig, SearchKind, TemperatureSchedule};
+use crate::color::Rgba;
+use crate::primitive::{self, Primitive};
+
+// Refines a single evaluated primitive against the current approximation and returns the best candidate found.
+pub trait SearchStrategy {
+
+    fn search(&self, approx: &ImageApproximation, start: Candidate, controller: &mut Controller) -> Candidate;
+}
+
+// Only accepts improvements and gives up after `max_attempts` mutation rounds in a row without one.
+pub struct HillClimbing;
+
+// Also accepts worse candidates, with a probability that shrinks as the temperature cools down over a fixed
+// number of iterations. Returns the best candidate seen, which may differ from the one the walk ended on.
+pub struct SimulatedAnnealing {
+    initial_temperature: f64,
+    final_temperature: f64,
+    schedule: TemperatureSchedule,
+    iterations: u32,
+}
+
+impl SimulatedAnnealing {
+
+    pub fn new(initial_temperature: f64, final_temperature: f64, schedule: TemperatureSchedule, iterations: u32) -> Self {
+        Self { initial_temperature, final_temperature, schedule, iterations }
+    }
+
+    // Temperature after the given fraction of the iterations.
+    pub fn temperature(&self, progress: f64) -> f64 {
+        let (t0, t1) = (self.initial_temperature.max(f64::MIN_POSITIVE), self.final_temperature.max(f64::MIN_POSITIVE));
+        match self.schedule {
+            TemperatureSchedule::Linear => t0 + (t1 - t0) * progress,
+            TemperatureSchedule::Exponential => t0 * (t1 / t0).powf(progress),
+        }
+    }
+
+    // Probability of moving to a candidate whose error per pixel is higher by `delta`. Improvements are always accepted.
+    pub fn acceptance_probability(&self, delta: f64, progress: f64) -> f64 {
+        if delta <= 0.0 {
+            return 1.0;
+        }
+        (-delta / self.temperature(progress)).exp()
+    }
+}
+
+// Evolves a population of candidates, seeded with the start candidate and new primitives from the controller.
+// Each generation keeps the elites and breeds the rest from parents picked by tournament selection.
+pub struct Genetic {
+    population_size: usize,
+    generations: u32,
+    tournament_size: usize,
+    elitism: usize,
+    crossover_rate: f64,
+    mutation_rate: f64,
+}
+
+impl Genetic {
+
+    pub fn new(population_size: u32, generations: u32, tournament_size: u32, elitism: u32, crossover_rate: f64, mutation_rate: f64) -> Self {
+        let population_size = population_size.max(2) as usize;
+        Self {
+            population_size,
+            generations,
+            tournament_size: tournament_size.max(1) as usize,
+            // At least the best candidate survives every generation.
+            elitism: (elitism as usize).clamp(1, population_size),
+            crossover_rate: crossover_rate.clamp(0.0, 1.0),
+            mutation_rate: mutation_rate.clamp(0.0, 1.0),
+        }
+    }
+
+    // The best of `tournament_size` randomly picked candidates.
+    fn tournament<'a>(&self, population: &'a [Candidate], controller: &mut Controller) -> &'a Candidate {
+        (0..self.tournament_size)
+            .map(|_| &population[controller.rng().gen_range(0..population.len())])
+            .min_by_key(|(_, render)| render.get_error_sum())
+            .unwrap()
+    }
+
+    // The elites of the population, best first, followed by the evaluated children bred from the whole population.
+    pub fn next_generation(&self, approx: &ImageApproximation, mut population: Vec<Candidate>, controller: &mut Controller) -> Vec<Candidate> {
+        population.sort_by_key(|(_, render)| render.get_error_sum());
+        let children = (self.elitism..self.population_size)
+            .map(|_| self.breed(&population, controller))
+            .collect::<Vec<Box<dyn Primitive>>>();
+        let children = approx.evaluate_candidates(children, controller.get_color_solving());
+
+        population.truncate(self.elitism);
+        population.extend(children.into_iter().flatten());
+        population
+    }
+
+    fn breed(&self, population: &[Candidate], controller: &mut Controller) -> Box<dyn Primitive> {
+        let first = self.tournament(population, controller).0.as_ref();
+        let mut child = if controller.rng().gen_bool(self.crossover_rate) {
+            let second = self.tournament(population, controller).0.as_ref();
+            first.crossover(second, controller).unwrap_or_else(|| first.box_clone())
+        } else {
+            first.box_clone()
+        };
+        if controller.rng().gen_bool(self.mutation_rate) {
+            child.mutate(controller);
+        }
+        child
+    }
+}
+
+pub fn strategy_for(config: &SearchConfig) -> Box<dyn SearchStrategy> {
+    match config.strategy {
+        SearchKind::HillClimbing => Box::new(HillClimbing),
+        SearchKind::SimulatedAnnealing => Box::new(SimulatedAnnealing::new(
+            config.initial_temperature,
+            config.final_temperature,
+            config.schedule,
+            config.iterations,
+        )),
+        SearchKind::Genetic => Box::new(Genetic::new(
+            config.population_size,
+            config.generations,
+            config.tournament_size,
+            config.elitism,
+            config.crossover_rate,
+            config.mutation_rate,
+        )),
+    }
+}
+
+// The best of a batch of mutations of the given primitive.
+fn best_mutation(approx: &ImageApproximation, primitive: &dyn Primitive, controller: &mut Controller) -> Option<Candidate> {
+    let candidates = (0..controller.get_batch_size()).map(|_| {
+        let mut new_primitive = primitive.box_clone();
+        new_primitive.mutate(controller);
+        new_primitive
+    }).collect::<Vec<Box<dyn Primitive>>>();
+
+    approx.evaluate_candidates(candidates, controller.get_color_solving()).into_iter()
+        .flatten()
+        .min_by_key(|(_, render)| render.get_error_sum())
+}
+
+impl SearchStrategy for HillClimbing {
+
+    fn search(&self, approx: &ImageApproximation, start: Candidate, controller: &mut Controller) -> Candidate {
+        let (mut top_stroke, mut top_render) = start;
+        let mut attempts = 0;
+        while attempts < controller.get_max_attempts() {
+            match best_mutation(approx, top_stroke.as_ref(), controller) {
+                Some((new_stroke, new_render)) if new_render.get_error_sum() < top_render.get_error_sum() => {
+                    top_stroke = new_stroke;
+                    top_render = new_render;
+                    attempts = 0;
+                },
+                _ => attempts += 1,
+            }
+        }
+        (top_stroke, top_render)
+    }
+}
+
+impl SearchStrategy for SimulatedAnnealing {
+
+    fn search(&self, approx: &ImageApproximation, start: Candidate, controller: &mut Controller) -> Candidate {
+        let (mut current_stroke, mut current_render) = (start.0.box_clone(), start.1.clone());
+        let mut best = start;
+        for i in 0..self.iterations {
+            let Some((new_stroke, new_render)) = best_mutation(approx, current_stroke.as_ref(), controller) else { continue };
+
+            // Error change per pixel of the candidate, so the temperature does not depend on the stroke size.
+            let delta = (new_render.get_error_sum() as f64 - current_render.get_error_sum() as f64) / new_render.area().max(1) as f64;
+            let probability = self.acceptance_probability(delta, i as f64 / self.iterations as f64);
+            let accepted = probability >= 1.0 || controller.rng().gen::<f64>() < probability;
+            if !accepted {
+                continue;
+            }
+            if new_render.get_error_sum() < best.1.get_error_sum() {
+                best = (new_stroke.box_clone(), new_render.clone());
+            }
+            current_stroke = new_stroke;
+            current_render = new_render;
+        }
+        best
+    }
+}
+
+impl SearchStrategy for Genetic {
+
+    fn search(&self, approx: &ImageApproximation, start: Candidate, controller: &mut Controller) -> Candidate {
+        let founders = (1..self.population_size).map(|_| {
+            let shape = controller.pick_shape();
+            let mut primitive = primitive::new_primitive(shape, approx.get_brushes(), controller);
+            primitive.set_color(Rgba { a: controller.get_alpha(), ..Rgba::new_black() });
+            primitive
+        }).collect::<Vec<Box<dyn Primitive>>>();
+        let mut population = vec![start];
+        population.extend(approx.evaluate_candidates(founders, controller.get_color_solving()).into_iter().flatten());
+
+        for _ in 0..self.generations {
+            population = self.next_generation(approx, population, controller);
+        }
+        population.into_iter().min_by_key(|(_, render)| render.get_error_sum()).unwrap()
+    }
+}
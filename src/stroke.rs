@@ -1,7 +1,17 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::sync::{Arc, OnceLock};
+
 use rand::Rng;
 
+use tiny_skia;
+
 use crate::Controller;
+use crate::brush::BrushLibrary;
 use crate::color::Rgba;
+use crate::config::AlphaFormat;
+use crate::primitive::{paint_attributes, Primitive};
+use crate::util;
 
 pub static STROKE_DIMENSION: (f32, f32) = (100.0, 100.0);
 pub static STROKES: [&str; 4] = [
@@ -11,6 +21,36 @@ pub static STROKES: [&str; 4] = [
     "<path id=\"stroke-3\" d=\"m 48.912675,25.437236 c -0.971854,15.434662 -11.121227,12.293489 -23.14783,17.392075 -14.248849,3.330562 -31.6143565,15.710521 -45.253383,5.911035 -7.414644,-7.554927 -20.428212,1.134862 -17.402389,-18.618008 -8.407549,-9.3515 -16.492269,-12.394635 -14.44589,-21.3947098 1.450007,-6.6542624 6.58843,-8.15769866 -0.648885,-17.7023052 -3.69553,-14.085295 9.755107,-30.341847 18.928697,-29.295234 14.011651,-3.414073 26.9089315,-7.172641 40.7256401,-8.976395 15.5810439,-3.566203 32.3391309,9.923948 32.5923619,18.234641 7.697508,8.322364 3.41215,13.901682 10.395603,30.2374764 C 40.43043,10.248548 45.7314,22.03557 48.912675,25.437236 Z\" />",
 ];
 
+static STROKE_PATHS: OnceLock<Vec<tiny_skia::Path>> = OnceLock::new();
+
+pub fn stroke_paths() -> &'static [tiny_skia::Path] {
+    STROKE_PATHS.get_or_init(|| {
+        STROKES.iter()
+            .map(|s| path_data_of(s).and_then(util::parse_path_data).expect("Built-in stroke paths must be valid"))
+            .collect()
+    })
+}
+
+fn path_data_of(path_element: &str) -> Option<&str> {
+    let start = path_element.find(" d=\"")? + 4;
+    let end = start + path_element[start..].find('"')?;
+    Some(&path_element[start..end])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    Translate,
+    Rotate,
+    Scale,
+    Template,
+    Skew,
+}
+
+impl Mutation {
+
+    pub const ALL: [Mutation; 5] = [Mutation::Translate, Mutation::Rotate, Mutation::Scale, Mutation::Template, Mutation::Skew];
+}
+
 pub struct Stroke {
     stroke_idx: usize,
     x: i32,
@@ -18,24 +58,40 @@ pub struct Stroke {
     rotation: i32,
     scale_x: f32,
     scale_y: f32,
+    skew: f32,
     color: Rgba,
+    brushes: Arc<BrushLibrary>,
 }
 
 impl Stroke {
 
-    pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::with_brushes(BrushLibrary::builtin(), rng)
+    }
+
+    // A stroke drawn with one of the given brushes instead of the built-in ones.
+    pub fn with_brushes<R: Rng + ?Sized>(brushes: Arc<BrushLibrary>, rng: &mut R) -> Self {
         Self {
-            stroke_idx: rng.gen_range(0..STROKES.len()) as usize,
+            stroke_idx: rng.gen_range(0..brushes.len()),
             x: 0,
             y: 0,
             rotation: 0,
             scale_x: 1.0,
             scale_y: 1.0,
+            skew: 0.0,
             color: Rgba::new_black(),
+            brushes,
         }
     }
 
+    pub fn get_stroke_idx(&self) -> usize {
+        self.stroke_idx
+    }
+
+    pub fn set_stroke_idx(&mut self, stroke_idx: usize) {
+        self.stroke_idx = stroke_idx % self.brushes.len();
+    }
+
     pub fn get_xy(&self) -> (i32, i32) {
         (self.x, self.y)
     }
@@ -54,38 +110,124 @@ impl Stroke {
         self.scale_y = scale.1;
     }
 
+    pub fn get_rotation(&self) -> i32 {
+        self.rotation
+    }
+
     pub fn set_rotation(&mut self, rotation: i32) {
         self.rotation = rotation;
     }
 
-    pub fn set_color(&mut self, color: Rgba) {
-        self.color = color;
+    pub fn get_skew(&self) -> f32 {
+        self.skew
+    }
+
+    pub fn set_skew(&mut self, skew: f32) {
+        self.skew = skew;
     }
+}
+
+impl Primitive for Stroke {
 
-    pub fn express(&self) -> String {
+    fn path(&self) -> Option<Cow<'_, tiny_skia::Path>> {
+        Some(Cow::Borrowed(self.brushes.get_path(self.stroke_idx)))
+    }
+
+    fn transform(&self) -> tiny_skia::Transform {
+        tiny_skia::Transform::from_translate(self.x as f32, self.y as f32)
+            .pre_rotate(self.rotation as f32)
+            .pre_concat(tiny_skia::Transform::from_skew(self.skew.to_radians().tan(), 0.0))
+            .pre_scale(self.scale_x, self.scale_y)
+    }
+
+    fn express_with(&self, alpha_format: AlphaFormat) -> String {
         let stroke = format!("<use href=\"#stroke-{}\"/>", self.stroke_idx);
-        let transformations = format!("translate({} {}) rotate({}) scale({:.5} {:.5})", self.x, self.y, self.rotation, self.scale_x, self.scale_y);
-        let color = self.color.as_hex();
-        format!("<g fill=\"{color}\" transform=\"{transformations}\">{stroke}</g>")
+        let skew = if self.skew != 0.0 { format!(" skewX({:.3})", self.skew) } else { String::new() };
+        let transformations = format!("translate({} {}) rotate({}){skew} scale({:.5} {:.5})", self.x, self.y, self.rotation, self.scale_x, self.scale_y);
+        let fill = paint_attributes("fill", &self.color, alpha_format);
+        format!("<g {fill} transform=\"{transformations}\">{stroke}</g>")
     }
 
-    pub fn mutate(&mut self, controller: &Controller) {
-        let mut rng = rand::thread_rng();
-        match rng.gen_range(0..=1) {
-            0 => {
-                let m = controller.get_mutation_movement();
+    fn get_color(&self) -> &Rgba {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Rgba) {
+        self.color = color;
+    }
+
+    fn box_clone(&self) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
+    // Uniform crossover: position, rotation, scale, template, skew and alpha are each taken from either parent.
+    // The color channels are not inherited, as every candidate is colored after the target when it is evaluated.
+    fn crossover(&self, other: &dyn Primitive, controller: &mut Controller) -> Option<Box<dyn Primitive>> {
+        let other = other.as_any().downcast_ref::<Stroke>()?;
+        let rng = controller.rng();
+        let mut child = self.clone();
+        if rng.gen_bool(0.5) {
+            child.set_xy(other.get_xy());
+        }
+        if rng.gen_bool(0.5) {
+            child.rotation = other.rotation;
+        }
+        if rng.gen_bool(0.5) {
+            child.set_scale(other.get_scale());
+        }
+        if rng.gen_bool(0.5) {
+            child.set_stroke_idx(other.stroke_idx);
+        }
+        if rng.gen_bool(0.5) {
+            child.skew = other.skew;
+        }
+        if rng.gen_bool(0.5) {
+            child.color.a = other.color.a;
+        }
+        Some(Box::new(child))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn mutate(&mut self, controller: &mut Controller) {
+        let m = controller.get_mutation_movement();
+        let r = controller.get_mutation_rotation();
+        let base_scale = controller.get_scale();
+        let config = controller.get_mutation_config().clone();
+        let mutation = controller.pick_mutation();
+        let rng = controller.rng();
+        match mutation {
+            Mutation::Translate => {
                 self.x += rng.gen_range(-m.0..m.1);
                 self.y += rng.gen_range(-m.0..m.1);
             },
-            1 => {
-                self.rotation += rng.gen_range(-90..90);
+            Mutation::Rotate => {
+                self.rotation += rng.gen_range(-r..r);
                 self.rotation %= 360;
             },
-            _ => panic!("Should be impossible. Check if range of random number properly matches the available options."),
+            Mutation::Scale => {
+                let step = config.scale_step.abs();
+                let (min_x, max_x) = scale_bounds(base_scale.0, config.min_scale, config.max_scale);
+                let (min_y, max_y) = scale_bounds(base_scale.1, config.min_scale, config.max_scale);
+                self.scale_x = (self.scale_x * (1.0 + rng.gen_range(-step..=step))).clamp(min_x, max_x);
+                self.scale_y = (self.scale_y * (1.0 + rng.gen_range(-step..=step))).clamp(min_y, max_y);
+            },
+            Mutation::Template => {
+                let brushes = self.brushes.len();
+                if brushes > 1 {
+                    self.stroke_idx = (self.stroke_idx + rng.gen_range(1..brushes)) % brushes;
+                }
+            },
+            Mutation::Skew => {
+                let (step, range) = (config.skew_step.abs(), config.skew_range.abs());
+                self.skew = (self.skew + rng.gen_range(-step..=step)).clamp(-range, range);
+            },
         }
     }
 
-    pub fn approximate_pixels(&self) -> Vec<(i32, i32)> {
+    fn approximate_pixels(&self) -> Vec<(i32, i32)> {
         let (width, height) = (
             ((STROKE_DIMENSION.0 * self.scale_x)/2.0).floor() as i32,
             ((STROKE_DIMENSION.1 * self.scale_y)/2.0).floor() as i32,
@@ -111,7 +253,15 @@ impl Clone for Stroke {
             rotation: self.rotation,
             scale_x: self.scale_x,
             scale_y: self.scale_y,
+            skew: self.skew,
             color: self.color.clone(),
+            brushes: self.brushes.clone(),
         }
     }
 }
+
+// Bounds of the scale of one axis, ordered so that clamping to them does not panic for inverted or negative factors.
+fn scale_bounds(base: f32, min_factor: f32, max_factor: f32) -> (f32, f32) {
+    let (a, b) = (base * min_factor, base * max_factor);
+    (a.min(b), a.max(b))
+}
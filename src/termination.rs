@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StopConditions {
+    pub max_strokes: Option<usize>,
+    pub target_fitness: Option<f64>,
+    #[serde(rename = "time_budget_secs", serialize_with = "serialize_secs", deserialize_with = "deserialize_secs")]
+    pub time_budget: Option<Duration>,
+    pub max_stages: Option<u32>,
+    pub max_iterations_without_improvement: Option<u32>,
+}
+
+fn serialize_secs<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.map(|d| d.as_secs_f64()).serialize(serializer)
+}
+
+fn deserialize_secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let secs = Option::<f64>::deserialize(deserializer)?;
+    match secs {
+        Some(s) => Duration::try_from_secs_f64(s).map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    MaxStrokes,
+    TargetFitness,
+    TimeBudget,
+    MaxStages,
+    NoImprovement,
+}
+
+impl StopReason {
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            StopReason::MaxStrokes => "MAX_STROKES",
+            StopReason::TargetFitness => "TARGET_FITNESS",
+            StopReason::TimeBudget => "TIME_BUDGET",
+            StopReason::MaxStages => "MAX_STAGES",
+            StopReason::NoImprovement => "NO_IMPROVEMENT",
+        }
+    }
+}
+
+pub struct Progress {
+    pub strokes: usize,
+    pub fitness: f64,
+    pub stage: u32,
+    pub iterations_without_improvement: u32,
+    pub elapsed: Duration,
+}
+
+impl StopConditions {
+
+    pub fn check(&self, progress: &Progress) -> Option<StopReason> {
+        if self.max_strokes.is_some_and(|n| progress.strokes >= n) {
+            return Some(StopReason::MaxStrokes);
+        }
+        if self.target_fitness.is_some_and(|f| progress.fitness <= f) {
+            return Some(StopReason::TargetFitness);
+        }
+        if self.max_stages.is_some_and(|n| progress.stage > n) {
+            return Some(StopReason::MaxStages);
+        }
+        if self.max_iterations_without_improvement.is_some_and(|n| progress.iterations_without_improvement >= n) {
+            return Some(StopReason::NoImprovement);
+        }
+        if self.time_budget.is_some_and(|t| progress.elapsed >= t) {
+            return Some(StopReason::TimeBudget);
+        }
+        None
+    }
+}
+
+// std::time::Instant panics on wasm32-unknown-unknown, so the browser clock is used there.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Stopwatch {
+    start: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Stopwatch {
+
+    pub fn start() -> Self {
+        Self { start: std::time::Instant::now() }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub struct Stopwatch {
+    start_ms: f64,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Stopwatch {
+
+    pub fn start() -> Self {
+        Self { start_ms: js_sys::Date::now() }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64((js_sys::Date::now() - self.start_ms).max(0.0) / 1000.0)
+    }
+}
@@ -1,4 +1,3 @@
-use std::path::Path;
 use rand::Rng;
 use std::collections::HashSet;
 
@@ -10,11 +9,13 @@ use tiny_skia_path;
 
 pub mod image;
 
+use crate::Error;
+
 pub fn bounded_add(a: u8, b: i64) -> u8 {
     if b > 0 {
-        a.checked_add(b as u8).unwrap_or(u8::MAX)
+        a.saturating_add(b as u8)
     } else {
-        a.checked_sub(-b as u8).unwrap_or(u8::MIN)
+        a.saturating_sub(-b as u8)
     }
 }
 
@@ -28,14 +29,13 @@ pub fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
     }
 }
 
-pub fn random_points_in_range(n: u64, start: u64, end: u64) -> Vec<u64> {
+pub fn random_points_in_range<R: Rng + ?Sized>(n: u64, start: u64, end: u64, rng: &mut R) -> Vec<u64> {
     let span = end - start;
     let n_safe = std::cmp::min(span, n);
     if span == n_safe {
         return (start..end).collect::<Vec<u64>>();
     }
 
-    let mut rng = rand::thread_rng();
     let mut numbers: HashSet<u64> = HashSet::new();
     numbers.insert(start);
     numbers.insert(end);
@@ -49,35 +49,88 @@ pub fn random_points_in_range(n: u64, start: u64, end: u64) -> Vec<u64> {
     points
 }
 
-pub fn read_image(path: &String) -> tiny_skia::Pixmap {
-    return tiny_skia::Pixmap::load_png(&Path::new(path)).expect("Failed to open image");
+pub fn parse_path_data(data: &str) -> Option<tiny_skia::Path> {
+    let mut builder = tiny_skia::PathBuilder::new();
+    for segment in svgtypes::SimplifyingPathParser::from(data) {
+        match segment.ok()? {
+            svgtypes::SimplePathSegment::MoveTo { x, y } => builder.move_to(x as f32, y as f32),
+            svgtypes::SimplePathSegment::LineTo { x, y } => builder.line_to(x as f32, y as f32),
+            svgtypes::SimplePathSegment::Quadratic { x1, y1, x, y } => builder.quad_to(x1 as f32, y1 as f32, x as f32, y as f32),
+            svgtypes::SimplePathSegment::CurveTo { x1, y1, x2, y2, x, y } => builder.cubic_to(x1 as f32, y1 as f32, x2 as f32, y2 as f32, x as f32, y as f32),
+            svgtypes::SimplePathSegment::ClosePath => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+// The inverse of parse_path_data, with absolute coordinates only.
+pub fn path_data(path: &tiny_skia::Path) -> String {
+    path.segments().map(|segment| match segment {
+        tiny_skia::PathSegment::MoveTo(p) => format!("M {:.3} {:.3}", p.x, p.y),
+        tiny_skia::PathSegment::LineTo(p) => format!("L {:.3} {:.3}", p.x, p.y),
+        tiny_skia::PathSegment::QuadTo(p1, p) => format!("Q {:.3} {:.3} {:.3} {:.3}", p1.x, p1.y, p.x, p.y),
+        tiny_skia::PathSegment::CubicTo(p1, p2, p) => format!("C {:.3} {:.3} {:.3} {:.3} {:.3} {:.3}", p1.x, p1.y, p2.x, p2.y, p.x, p.y),
+        tiny_skia::PathSegment::Close => String::from("Z"),
+    }).collect::<Vec<String>>().join(" ")
+}
+
+pub fn read_image(path: &String) -> Result<tiny_skia::Pixmap, Error> {
+    let decoded = ::image::open(path).map_err(|e| match e {
+        ::image::ImageError::IoError(source) => Error::Io { path: path.clone(), source },
+        ::image::ImageError::Unsupported(u) => Error::UnsupportedImage { path: path.clone(), message: u.to_string() },
+        e => Error::ImageDecode { path: path.clone(), message: e.to_string() },
+    })?;
+    let rgba = decoded.to_rgba8();
+    pixmap_from_rgba(rgba.width(), rgba.height(), rgba.as_raw())
+        .ok_or(Error::InvalidDimensions { width: rgba.width(), height: rgba.height() })
+}
+
+// Converts straight (non-premultiplied) RGBA bytes, as produced by decoders and canvases, into a pixmap.
+pub fn pixmap_from_rgba(width: u32, height: u32, data: &[u8]) -> Option<tiny_skia::Pixmap> {
+    if data.len() != width as usize * height as usize * 4 {
+        return None;
+    }
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)?;
+    for (pixel, rgba) in pixmap.pixels_mut().iter_mut().zip(data.chunks_exact(4)) {
+        *pixel = tiny_skia::ColorU8::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3]).premultiply();
+    }
+    Some(pixmap)
 }
 
-pub fn render_svg_into_pixmap(svg_data: &String, pixmap: &mut tiny_skia::Pixmap) {
+pub fn render_svg_into_pixmap(svg_data: &str, pixmap: &mut tiny_skia::Pixmap) -> Result<(), Error> {
     let opt = usvg::Options::default();
     let db = fontdb::Database::new();
 
-    let tree = usvg::Tree::from_str(&svg_data, &opt, &db).unwrap();
+    let tree = usvg::Tree::from_str(svg_data, &opt, &db)?;
     let transformation = tiny_skia_path::Transform::identity();
     resvg::render(&tree, transformation, &mut pixmap.as_mut());
+    Ok(())
 }
 
-pub fn pixmap_distance(d1: &tiny_skia::Pixmap, d2: &tiny_skia::Pixmap) -> f64 {
+pub fn check_same_dimensions(d1: &tiny_skia::Pixmap, d2: &tiny_skia::Pixmap) -> Result<(), Error> {
     if d1.width() != d2.width() || d1.height() != d2.height() {
-        panic!("Pixmaps of different dimensions can not be compared. Got {}x{} and {}x{}.", d1.width(), d1.height(), d2.width(), d2.height());
+        return Err(Error::DimensionMismatch { left: (d1.width(), d1.height()), right: (d2.width(), d2.height()) });
     }
-    let pixels1 = d1.pixels();
-    let pixels2 = d2.pixels();
-    return (0..pixels1.len()).map(|i| coloru8_norm_2(&pixels1[i], &pixels2[i]) as f64).map(|i| i*i).sum::<f64>().sqrt();
+    Ok(())
+}
+
+pub fn pixmap_distance(d1: &tiny_skia::Pixmap, d2: &tiny_skia::Pixmap) -> Result<f64, Error> {
+    pixmap_distance_with_importance(d1, d2, None)
+}
+
+// See fitness::distance, which also supports the other metrics.
+pub fn pixmap_distance_with_importance(d1: &tiny_skia::Pixmap, d2: &tiny_skia::Pixmap, importance: Option<&[u8]>) -> Result<f64, Error> {
+    crate::fitness::distance(&crate::fitness::RgbL2, d1, d2, importance)
 }
 
-fn coloru8_norm_2(c1: &tiny_skia::PremultipliedColorU8, c2: &tiny_skia::PremultipliedColorU8) -> f64 {
+// Squared per-pixel distance, the error of fitness::RgbL2.
+pub fn coloru8_distance_sq(c1: &tiny_skia::PremultipliedColorU8, c2: &tiny_skia::PremultipliedColorU8) -> u32 {
     if c1.alpha() == c2.alpha() {
         let dr = c1.red() as i32 - c2.red() as i32;
         let dg = c1.green() as i32 - c2.green() as i32;
         let db = c1.blue() as i32 - c2.blue() as i32;
-        ((dr*dr + dg*dg + db*db) as f64).sqrt() as f64
+        (dr*dr + dg*dg + db*db) as u32
     } else {
-        ((255.0*255.0*3.0) as f64).sqrt()
+        255*255*3
     }
 }
@@ -3,10 +3,10 @@ use rand_distr::Distribution;
 
 use tiny_skia;
 
+use crate::Error;
 use crate::util;
 
-static PI: f64 = 3.14159265359;
-static EULER_E: f64 = 2.71828182846;
+use std::f64::consts::{E as EULER_E, PI};
 
 fn rgba_to_grayscale(c: &tiny_skia::PremultipliedColorU8) -> i32 {
     rgb_to_grayscale(&c.demultiply())
@@ -16,6 +16,32 @@ fn rgb_to_grayscale(c: &tiny_skia::ColorU8) -> i32 {
     (c.red() as f64 * 0.299 + c.green() as f64 * 0.587 + c.blue() as f64 * 0.114) as i32
 }
 
+// Gray value of every pixel, row by row.
+pub fn graylevels(input: &tiny_skia::Pixmap) -> Vec<f64> {
+    input.pixels().iter().map(|p| rgba_to_grayscale(p) as f64).collect()
+}
+
+// Weight of black pixels of an importance mask. Without it, black regions would never be painted at all.
+pub const MIN_IMPORTANCE: u8 = 16;
+
+// Importance weights of every pixel, row by row, from the gray level of a mask, mapped onto MIN_IMPORTANCE..=255.
+pub fn importance_weights(mask: &tiny_skia::Pixmap) -> Vec<u8> {
+    let floor = MIN_IMPORTANCE as i32;
+    mask.pixels().iter()
+        .map(|p| (floor + rgba_to_grayscale(p).clamp(0, 255) * (255 - floor) / 255) as u8)
+        .collect()
+}
+
+// Copies a region between two pixmaps of the same size.
+pub fn copy_rect(src: &tiny_skia::Pixmap, dst: &mut tiny_skia::Pixmap, rect: &tiny_skia::IntRect) {
+    let width = src.width() as usize;
+    let (x0, x1) = (rect.x() as usize, rect.right() as usize);
+    for y in rect.y() as usize..rect.bottom() as usize {
+        let row = y * width;
+        dst.pixels_mut()[row + x0..row + x1].copy_from_slice(&src.pixels()[row + x0..row + x1]);
+    }
+}
+
 fn get_canvas(input: &tiny_skia::Pixmap) -> (tiny_skia::Pixmap, i32, i32){
     let width = input.width() as i32;
     let height = input.height() as i32;
@@ -26,7 +52,7 @@ fn get_canvas(input: &tiny_skia::Pixmap) -> (tiny_skia::Pixmap, i32, i32){
 pub fn sobel(input: &tiny_skia::Pixmap) -> tiny_skia::Pixmap {
     let def = tiny_skia::PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
 
-    let (mut canvas, width, height) = get_canvas(&input);
+    let (mut canvas, width, height) = get_canvas(input);
     let data = canvas.pixels_mut();
 
     let get_pixel = |x: i32, y: i32| -> i32 {
@@ -56,7 +82,7 @@ pub fn sobel(input: &tiny_skia::Pixmap) -> tiny_skia::Pixmap {
         }
     }
 
-    return canvas;
+    canvas
 }
 
 pub fn gaussian_blur(input: &tiny_skia::Pixmap) -> tiny_skia::Pixmap {
@@ -68,10 +94,10 @@ pub fn gaussian_blur_from_gaussian_function(input: &tiny_skia::Pixmap, sigma: f6
     gaussian_blur_with_kernel(input, &kernel)
 }
 
-pub fn gaussian_blur_with_kernel(input: &tiny_skia::Pixmap, kernel: &Vec<Vec<f64>>) -> tiny_skia::Pixmap {
+pub fn gaussian_blur_with_kernel(input: &tiny_skia::Pixmap, kernel: &[Vec<f64>]) -> tiny_skia::Pixmap {
     let def = tiny_skia::PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
 
-    let (mut canvas, width, height) = get_canvas(&input);
+    let (mut canvas, width, height) = get_canvas(input);
     let data = canvas.pixels_mut();
 
     let kernel_width = kernel[0].len() as i32;
@@ -104,7 +130,7 @@ pub fn gaussian_blur_with_kernel(input: &tiny_skia::Pixmap, kernel: &Vec<Vec<f64
         }
     }
 
-    return canvas;
+    canvas
 }
 
 fn get_gaussian_blur_kernel(sigma: f64, matrix_radius: u32) -> Vec<Vec<f64>> {
@@ -132,15 +158,13 @@ fn get_gaussian_blur_kernel(sigma: f64, matrix_radius: u32) -> Vec<Vec<f64>> {
         }
     }
 
-    return kernel;
+    kernel
 }
 
-pub fn abs_diff_in_graylevel(pixmap1: &tiny_skia::Pixmap, pixmap2: &tiny_skia::Pixmap) -> tiny_skia::Pixmap {
-    if pixmap1.width() != pixmap2.width() || pixmap1.height() != pixmap2.height() {
-        panic!("Can not get difference of two images of different dimensions.");
-    }
+pub fn abs_diff_in_graylevel(pixmap1: &tiny_skia::Pixmap, pixmap2: &tiny_skia::Pixmap) -> Result<tiny_skia::Pixmap, Error> {
+    util::check_same_dimensions(pixmap1, pixmap2)?;
 
-    let (mut canvas, width, height) = get_canvas(&pixmap1);
+    let (mut canvas, width, height) = get_canvas(pixmap1);
     let data = canvas.pixels_mut();
 
     let max_diff = 255.0 * 3.0;
@@ -165,7 +189,7 @@ pub fn abs_diff_in_graylevel(pixmap1: &tiny_skia::Pixmap, pixmap2: &tiny_skia::P
         }
     }
 
-    return canvas;
+    Ok(canvas)
 }
 
 pub struct GraylevelMask {
@@ -177,25 +201,29 @@ pub struct GraylevelMask {
 impl GraylevelMask {
 
     pub fn from(src: &tiny_skia::Pixmap) -> Self {
-        let gray = src.pixels().iter().map(|p| rgba_to_grayscale(p) as f64).collect::<Vec<f64>>();
+        Self::from_weights(&graylevels(src), src.width(), src.height())
+    }
+
+    // Samples pixels proportionally to the given row by row weights. Without any positive weight, sampling is uniform.
+    pub fn from_weights(weights: &[f64], width: u32, height: u32) -> Self {
         Self {
-            dist: match rand_distr::WeightedIndex::new(&gray) {
+            dist: match rand_distr::WeightedIndex::new(weights) {
                 Ok(d) => d,
-                Err(_) => rand_distr::WeightedIndex::new(&vec![1.0; gray.len()]).unwrap(),
+                Err(_) => rand_distr::WeightedIndex::new(vec![1.0; weights.len()]).unwrap(),
             },
-            width: src.width(),
-            height: src.height(),
+            width,
+            height,
         }
     }
 
-    pub fn sample_random_i(&self) -> u32 {
-        let mut rng = rand::thread_rng();
-        self.dist.sample(&mut rng) as u32
+    pub fn sample_random_i<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> u32 {
+        self.dist.sample(rng) as u32
     }
 
-    pub fn sample_random_xy(&self) -> (u32, u32) {
-        let i = self.sample_random_i();
-        ((i as f64 / self.height as f64).floor() as u32, i % self.height)
+    // Pixels are stored row by row, so the index is y * width + x.
+    pub fn sample_random_xy<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> (u32, u32) {
+        let i = self.sample_random_i(rng);
+        (i % self.width, i / self.width)
     }
 
     pub fn dim(&self) -> (u32, u32) {
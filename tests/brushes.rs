@@ -0,0 +1,77 @@
+mod common;
+
+use std::sync::Arc;
+
+use svg_painter::Error;
+use svg_painter::brush::BrushLibrary;
+use svg_painter::config::PainterConfig;
+use svg_painter::painter::Painter;
+use svg_painter::util;
+
+const BRUSHES: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300">
+<g transform="translate(200 20) scale(2)">
+<rect x="10" y="10" width="40" height="10"/>
+<path d="M 0 0 L 30 80 L -20 60 Z"/>
+</g>
+<circle cx="300" cy="200" r="5"/>
+</svg>"#;
+
+#[test]
+fn brushes_are_normalized_and_centered() {
+    let dir = std::env::temp_dir().join(format!("svg-painter-brushes-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("pack.svg"), BRUSHES).unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a brush").unwrap();
+
+    let brushes = BrushLibrary::from_path(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(brushes.len(), 3);
+    for i in 0..brushes.len() {
+        let bounds = brushes.get_path(i).bounds();
+        assert!((bounds.width().max(bounds.height()) - 100.0).abs() < 0.01, "{bounds:?}");
+        assert!((bounds.left() + bounds.right()).abs() < 0.01 && (bounds.top() + bounds.bottom()).abs() < 0.01, "{bounds:?}");
+    }
+}
+
+#[test]
+fn custom_brushes_are_rendered_like_their_svg() {
+    let (width, height) = (100, 70);
+    let target = common::gradient_target(width, height);
+    let mut painter = Painter::new(target.clone(), &common::seeded_config(9, 25));
+    painter.set_brushes(Arc::new(BrushLibrary::from_svg(BRUSHES).unwrap()));
+    let (approx, _) = svg_painter::run_painter(painter, |_| {});
+
+    let svg = approx.express();
+    assert!(svg.contains("<path id=\"stroke-2\"") && !svg.contains("stroke-3"));
+    let mut svg_render = tiny_skia::Pixmap::new(width, height).unwrap();
+    util::render_svg_into_pixmap(&svg, &mut svg_render).unwrap();
+    let svg_fitness = util::pixmap_distance(&svg_render, &target).unwrap();
+    assert!((svg_fitness - approx.get_fitness()).abs() <= approx.get_fitness() * 0.01, "{svg_fitness} vs {}", approx.get_fitness());
+}
+
+#[test]
+fn swapping_brushes_mid_run_keeps_the_svg_consistent() {
+    let target = common::gradient_target(80, 60);
+    let brushes = Arc::new(BrushLibrary::from_svg(BRUSHES).unwrap());
+
+    let mut approx = common::seeded_run(&target, 4, 10);
+    assert!(matches!(approx.set_brushes(brushes.clone()), Err(Error::InvalidBrushes(_))));
+
+    let mut painter = Painter::new(target, &PainterConfig { seed: Some(4), ..PainterConfig::default() });
+    while painter.approximation().stroke_count() < 10 {
+        painter.step();
+    }
+    painter.set_brushes(brushes);
+    assert_eq!(painter.approximation().stroke_count(), 0);
+    while painter.approximation().stroke_count() < 10 {
+        painter.step();
+    }
+
+    // Every brush a stroke refers to is defined, and only the ones of the new library are.
+    let svg = painter.approximation().express();
+    for id in svg.split("href=\"#").skip(1).map(|s| &s[..s.find('"').unwrap()]) {
+        assert!(svg.contains(&format!("<path id=\"{id}\"")), "{id} is not defined");
+    }
+    assert!(!svg.contains("stroke-3"));
+}
@@ -0,0 +1,11 @@
+mod common;
+
+#[test]
+fn stroke_colors_track_their_footprint() {
+    // On a gradient, a moved stroke covers a different average color.
+    let approx = common::seeded_run(&common::gradient_target(80, 80), 7, 20);
+    assert!(approx.stroke_count() > 0);
+    for stroke in approx.get_strokes() {
+        assert_eq!(Some(stroke.get_color().clone()), approx.average_color_in_stroke(stroke.as_ref()));
+    }
+}
@@ -0,0 +1,36 @@
+// Every test crate uses only some of the helpers.
+#![allow(dead_code)]
+
+use svg_painter::approximation::ImageApproximation;
+use svg_painter::config::PainterConfig;
+
+// A target without large flat areas, so every run has plenty to paint.
+pub fn pattern_target(width: u32, height: u32) -> tiny_skia::Pixmap {
+    let mut target = tiny_skia::Pixmap::new(width, height).unwrap();
+    for (i, pixel) in target.pixels_mut().iter_mut().enumerate() {
+        let v = ((i * 37) % 251) as u8;
+        *pixel = tiny_skia::PremultipliedColorU8::from_rgba(v, 255 - v, v / 2, 255).unwrap();
+    }
+    target
+}
+
+// Red grows to the right and green to the bottom, so strokes at different places get different colors.
+pub fn gradient_target(width: u32, height: u32) -> tiny_skia::Pixmap {
+    let mut target = tiny_skia::Pixmap::new(width, height).unwrap();
+    for (i, pixel) in target.pixels_mut().iter_mut().enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        *pixel = tiny_skia::PremultipliedColorU8::from_rgba((x * 255 / width) as u8, (y * 255 / height) as u8, 128, 255).unwrap();
+    }
+    target
+}
+
+// Default config of a reproducible run that stops after the given number of strokes.
+pub fn seeded_config(seed: u64, strokes: usize) -> PainterConfig {
+    let mut config = PainterConfig { seed: Some(seed), ..PainterConfig::default() };
+    config.stop.max_strokes = Some(strokes);
+    config
+}
+
+pub fn seeded_run(target: &tiny_skia::Pixmap, seed: u64, strokes: usize) -> ImageApproximation {
+    svg_painter::run(target, &seeded_config(seed, strokes), |_| {}).0
+}
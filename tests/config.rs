@@ -0,0 +1,63 @@
+#![cfg(feature = "toml")]
+
+use std::time::Duration;
+
+use svg_painter::Error;
+use svg_painter::config::{PainterConfig, SearchKind};
+
+#[test]
+fn loads_partial_toml_and_json() {
+    let toml = r#"
+        batch_size = 4
+        [search]
+        strategy = "genetic"
+        [stop]
+        max_strokes = 100
+        time_budget_secs = 1.5
+    "#;
+    let config = PainterConfig::from_toml(toml).unwrap();
+    assert_eq!(config.batch_size, 4);
+    assert_eq!(config.search.strategy, SearchKind::Genetic);
+    assert_eq!(config.stop.max_strokes, Some(100));
+    assert_eq!(config.stop.time_budget, Some(Duration::from_millis(1500)));
+    assert_eq!(config.max_attempts, PainterConfig::default().max_attempts);
+
+    let json = r#"{ "stop": { "time_budget_secs": 2 } }"#;
+    assert_eq!(PainterConfig::from_json(json).unwrap().stop.time_budget, Some(Duration::from_secs(2)));
+
+    let config = PainterConfig::default();
+    assert_eq!(PainterConfig::from_toml(&config.to_toml()).unwrap(), config);
+    assert_eq!(PainterConfig::from_json(&config.to_json()).unwrap(), config);
+}
+
+#[test]
+fn rejects_invalid_values() {
+    let invalid = [
+        "scale_divisor = -8.0",
+        "scale_divisor = 0.0",
+        "[mutation]\nmin_scale = 0.0",
+        "[mutation]\nmax_scale = -1.0",
+        "[mutation]\nmin_scale = 3.0\nmax_scale = 2.0",
+        "[mutation]\nmovement_factor = -1.0",
+        "[mutation]\nmovement_factor = inf",
+        "[mutation]\nscale_step = 1e39",
+        "[mutation]\nscale_step = 1.0",
+        "[mutation]\nskew_range = nan",
+        "[mutation]\nskew_range = 90.0",
+        "[mutation]\nskew_step = 1e39",
+        "[mutation]\nskew_step = -5.0",
+        "ssim_window = 0",
+        "[search]\npopulation_size = 0",
+        "[search]\ninitial_temperature = inf",
+        "[search]\nfinal_temperature = nan",
+        "[search]\ncrossover_rate = nan",
+        "[search]\ncrossover_rate = -0.1",
+        "[search]\nmutation_rate = 1.5",
+        "[stop]\ntime_budget_secs = -1.0",
+        "batch_size = \"many\"",
+    ];
+    for toml in invalid {
+        assert!(matches!(PainterConfig::from_toml(toml), Err(Error::Config(_))), "accepted {toml:?}");
+    }
+    assert!(matches!(PainterConfig::from_json(r#"{ "ssim_window": 0 }"#), Err(Error::Config(_))));
+}
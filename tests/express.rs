@@ -0,0 +1,19 @@
+mod common;
+
+#[test]
+fn strokes_since_extend_an_earlier_svg() {
+    let target = common::gradient_target(40, 30);
+    let mut prefix = None;
+    let (approx, _) = svg_painter::run(&target, &common::seeded_config(3, 8), |img_approx| {
+        if img_approx.stroke_count() == 3 {
+            prefix = Some(img_approx.express());
+        }
+    });
+    let prefix = prefix.unwrap();
+
+    // Appends the new strokes inside the <svg> element, like the web page does.
+    let body = prefix.strip_suffix("\n</svg>").unwrap();
+    let increments = approx.express_strokes_since(3);
+    assert_eq!(increments.len(), 5);
+    assert_eq!(format!("{body}\n{}\n</svg>", increments.join("\n")), approx.express());
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use svg_painter::Controller;
+use svg_painter::approximation::ImageApproximation;
+use svg_painter::color::Rgba;
+use svg_painter::config::{PainterConfig, ShapeWeights};
+use svg_painter::primitive;
+use svg_painter::util;
+
+#[test]
+fn incremental_fitness_equals_full_recompute() {
+    let target = common::gradient_target(120, 80);
+    let config = PainterConfig {
+        seed: Some(8),
+        shapes: ShapeWeights { stroke: 1.0, triangle: 1.0, rectangle: 1.0, ellipse: 1.0, circle: 1.0, line: 1.0, polygon: 1.0 },
+        ..PainterConfig::default()
+    };
+    let mut controller = Controller::new(&target, &config);
+    let mut approx = ImageApproximation::new(target.clone());
+
+    for scale in [0.6, 0.3, 0.1] {
+        controller.set_scale((scale, scale));
+        // Every candidate is scored exactly like a full render of the approximation with it.
+        for _ in 0..200 {
+            let shape = controller.pick_shape();
+            let mut primitive = primitive::new_primitive(shape, approx.get_brushes(), &mut controller);
+            primitive.set_color(Rgba { a: controller.get_alpha(), ..Rgba::new_black() });
+            let Some((primitive, render)) = approx.evaluate_candidate(primitive, controller.get_color_solving()) else { continue };
+            let expected = util::pixmap_distance(&approx.get_render_with_stroke(primitive.as_ref()), &target).unwrap();
+            assert_eq!(render.fitness(), expected, "{}", primitive.express());
+        }
+        for _ in 0..5 {
+            approx.add_stroke(&mut controller);
+            assert_eq!(approx.get_fitness(), util::pixmap_distance(approx.get_render(), &target).unwrap());
+        }
+    }
+}
+
+#[test]
+fn render_equals_a_render_of_all_strokes() {
+    let target = common::gradient_target(120, 80);
+    let config = PainterConfig {
+        shapes: ShapeWeights { stroke: 1.0, triangle: 1.0, rectangle: 1.0, ellipse: 1.0, circle: 1.0, line: 1.0, polygon: 1.0 },
+        ..common::seeded_config(0, 40)
+    };
+    let (approx, _) = svg_painter::run(&target, &config, |_| {});
+
+    let mut render = tiny_skia::Pixmap::new(target.width(), target.height()).unwrap();
+    for stroke in approx.get_strokes() {
+        stroke.render(&mut render);
+    }
+    assert!(render.pixels() == approx.get_render().pixels());
+}
+
+#[test]
+fn new_approximation_starts_from_the_error_of_the_empty_canvas() {
+    let target = common::gradient_target(40, 30);
+    let approx = ImageApproximation::new(target.clone());
+    let empty = tiny_skia::Pixmap::new(40, 30).unwrap();
+    assert_eq!(approx.get_fitness(), util::pixmap_distance(&empty, &target).unwrap());
+
+    // Nothing improves on a transparent target, not even the first stroke.
+    let transparent = tiny_skia::Pixmap::new(40, 30).unwrap();
+    let mut approx = ImageApproximation::new(transparent.clone());
+    let mut controller = Controller::new(&transparent, &PainterConfig { seed: Some(2), ..PainterConfig::default() });
+    controller.set_scale((0.2, 0.2));
+    assert!(!approx.add_stroke(&mut controller));
+    assert_eq!(approx.stroke_count(), 0);
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use svg_painter::Error;
+use svg_painter::util;
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("svg-painter-image-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn reads_non_png_formats() {
+    let mut source = image::RgbImage::new(3, 2);
+    for (x, y, pixel) in source.enumerate_pixels_mut() {
+        *pixel = image::Rgb([(x * 80) as u8, (y * 120) as u8, 40]);
+    }
+
+    let bmp = temp_path("target.bmp");
+    source.save(&bmp).unwrap();
+    let pixmap = util::read_image(&bmp.display().to_string()).unwrap();
+    assert_eq!((pixmap.width(), pixmap.height()), (3, 2));
+    let pixel = pixmap.pixel(2, 1).unwrap();
+    assert_eq!((pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()), (160, 120, 40, 255));
+
+    let jpeg = temp_path("target.jpg");
+    source.save(&jpeg).unwrap();
+    let pixmap = util::read_image(&jpeg.display().to_string()).unwrap();
+    assert_eq!((pixmap.width(), pixmap.height()), (3, 2));
+    assert!(pixmap.pixels().iter().all(|p| p.alpha() == 255));
+
+    std::fs::remove_file(bmp).unwrap();
+    std::fs::remove_file(jpeg).unwrap();
+}
+
+#[test]
+fn straight_alpha_is_premultiplied() {
+    let pixmap = util::pixmap_from_rgba(2, 1, &[200, 100, 50, 128, 10, 20, 30, 0]).unwrap();
+    let (first, second) = (pixmap.pixel(0, 0).unwrap(), pixmap.pixel(1, 0).unwrap());
+    assert_eq!((first.red(), first.green(), first.blue(), first.alpha()), (100, 50, 25, 128));
+    assert_eq!((second.red(), second.green(), second.blue(), second.alpha()), (0, 0, 0, 0));
+
+    assert!(util::pixmap_from_rgba(2, 1, &[0; 4]).is_none());
+}
+
+#[test]
+fn decode_errors_are_mapped() {
+    let unsupported = temp_path("target.xyz");
+    std::fs::write(&unsupported, "not an image").unwrap();
+    assert!(matches!(util::read_image(&unsupported.display().to_string()), Err(Error::UnsupportedImage { .. })));
+
+    let corrupt = temp_path("corrupt.png");
+    std::fs::write(&corrupt, "not a png").unwrap();
+    assert!(matches!(util::read_image(&corrupt.display().to_string()), Err(Error::ImageDecode { .. })));
+
+    let missing = temp_path("missing.png");
+    assert!(matches!(util::read_image(&missing.display().to_string()), Err(Error::Io { .. })));
+
+    std::fs::remove_file(unsupported).unwrap();
+    std::fs::remove_file(corrupt).unwrap();
+}
+
+#[test]
+fn canvas_data_is_checked() {
+    assert!(matches!(svg_painter::pixmap_from_canvas_data(&[], 0, 4), Err(Error::InvalidDimensions { width: 0, height: 4 })));
+    assert!(matches!(svg_painter::pixmap_from_canvas_data(&[0; 15], 2, 2), Err(Error::InvalidPixelData(_))));
+    assert!(matches!(svg_painter::pixmap_from_canvas_data(&[0; 17], 2, 2), Err(Error::InvalidPixelData(_))));
+    assert!(svg_painter::pixmap_from_canvas_data(&[0; 16], 2, 2).is_ok());
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use svg_painter::config::PainterConfig;
+use svg_painter::painter::{Painter, Step};
+use svg_painter::util;
+
+#[test]
+fn importance_weights_fitness_and_placement() {
+    let (width, height) = (60, 40);
+    let target = common::pattern_target(width, height);
+    // Only the left third matters.
+    let mut mask = tiny_skia::Pixmap::new(width, height).unwrap();
+    for (i, pixel) in mask.pixels_mut().iter_mut().enumerate() {
+        let v = if (i as u32 % width) < width / 3 { 255 } else { 0 };
+        *pixel = tiny_skia::PremultipliedColorU8::from_rgba(v, v, v, 255).unwrap();
+    }
+
+    let mut config = PainterConfig {
+        seed: Some(4),
+        ..PainterConfig::default()
+    };
+    config.stop.max_strokes = Some(10);
+    let mut painter = Painter::new(target.clone(), &config);
+    painter.set_importance(&mask).unwrap();
+    while !matches!(painter.step(), Step::Stopped(_)) {}
+    let approx = painter.approximation();
+
+    let weights = util::image::importance_weights(&mask);
+    let expected = util::pixmap_distance_with_importance(approx.get_render(), &target, Some(&weights)).unwrap();
+    assert!((expected - approx.get_fitness()).abs() < 1e-6, "{expected} vs {}", approx.get_fitness());
+
+    // Every stroke starts in the important region, so that is where the paint ends up.
+    let painted = |x_range: std::ops::Range<u32>| {
+        (0..height).flat_map(|y| x_range.clone().map(move |x| (x, y)))
+            .filter(|(x, y)| approx.get_render().pixel(*x, *y).unwrap().alpha() > 0)
+            .count()
+    };
+    assert!(painted(0..width / 3) > painted(2 * width / 3..width));
+}
+
+#[test]
+fn black_regions_are_still_painted() {
+    let target = common::pattern_target(60, 40);
+    let mut mask = tiny_skia::Pixmap::new(60, 40).unwrap();
+    mask.fill(tiny_skia::Color::BLACK);
+    assert!(util::image::importance_weights(&mask).iter().all(|w| *w == util::image::MIN_IMPORTANCE));
+
+    let mut config = PainterConfig {
+        seed: Some(4),
+        ..PainterConfig::default()
+    };
+    config.stop.max_strokes = Some(5);
+    config.stop.max_iterations_without_improvement = Some(100);
+    let mut painter = Painter::new(target, &config);
+    painter.set_importance(&mask).unwrap();
+    while !matches!(painter.step(), Step::Stopped(_)) {}
+    assert_eq!(painter.approximation().stroke_count(), 5);
+}
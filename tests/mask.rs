@@ -0,0 +1,69 @@
+use rand::SeedableRng;
+
+use svg_painter::approximation::ImageApproximation;
+use svg_painter::config::PlacementWeights;
+use svg_painter::placement::Placement;
+use svg_painter::util::image::GraylevelMask;
+
+fn gray(v: u8) -> tiny_skia::PremultipliedColorU8 {
+    tiny_skia::PremultipliedColorU8::from_rgba(v, v, v, 255).unwrap()
+}
+
+#[test]
+fn samples_follow_mask_weights_on_rectangular_images() {
+    for (width, height) in [(40, 10), (10, 40), (37, 23)] {
+        // Black everywhere except a bright block in the lower right and a dimmer single column on the left.
+        let mut src = tiny_skia::Pixmap::new(width, height).unwrap();
+        let (bright_x, bright_y) = (width * 3 / 4, height * 3 / 4);
+        for (i, pixel) in src.pixels_mut().iter_mut().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            *pixel = if x >= bright_x && y >= bright_y {
+                gray(200)
+            } else if x == 1 {
+                gray(100)
+            } else {
+                gray(0)
+            };
+        }
+        let bright_weight = ((width - bright_x) * (height - bright_y)) as f64 * 200.0;
+        let column_weight = height as f64 * 100.0;
+        let expected_bright = bright_weight / (bright_weight + column_weight);
+
+        let mask = GraylevelMask::from(&src);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(3);
+        let samples = 20000;
+        let mut bright = 0;
+        for _ in 0..samples {
+            let (x, y) = mask.sample_random_xy(&mut rng);
+            assert!(x < width && y < height, "({x}, {y}) outside of {width}x{height}");
+            let weight = src.pixel(x, y).unwrap().red();
+            assert!(weight > 0, "sampled ({x}, {y}) with zero weight on {width}x{height}");
+            if weight == 200 {
+                bright += 1;
+            }
+        }
+        let ratio = bright as f64 / samples as f64;
+        assert!((ratio - expected_bright).abs() < 0.02, "{ratio} of samples in the bright block, expected {expected_bright} on {width}x{height}");
+    }
+}
+
+#[test]
+fn edge_placement_samples_along_target_edges() {
+    let (width, height) = (48, 20);
+    let mut target = tiny_skia::Pixmap::new(width, height).unwrap();
+    for (i, pixel) in target.pixels_mut().iter_mut().enumerate() {
+        *pixel = if i as u32 % width < width / 2 { gray(0) } else { gray(255) };
+    }
+    let approx = ImageApproximation::new(target.clone());
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(9);
+
+    let edges = Placement::new(&target, &PlacementWeights { error: 0.0, edge_error: 1.0, ..PlacementWeights::default() }).mask(&approx);
+    for _ in 0..2000 {
+        let (x, _) = edges.sample_random_xy(&mut rng);
+        assert!(x.abs_diff(width / 2) <= 1, "sampled column {x} away from the edge");
+    }
+
+    let uniform = Placement::new(&target, &PlacementWeights { error: 0.0, uniform: 1.0, ..PlacementWeights::default() }).mask(&approx);
+    let near_edge = (0..2000).filter(|_| uniform.sample_random_xy(&mut rng).0.abs_diff(width / 2) <= 1).count();
+    assert!(near_edge < 300, "{near_edge} uniform samples next to the edge");
+}
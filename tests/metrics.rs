@@ -0,0 +1,38 @@
+mod common;
+
+use svg_painter::config::{MetricKind, PainterConfig};
+use svg_painter::fitness;
+
+#[test]
+fn delta_e2000_matches_reference_data() {
+    // Pairs from the test data of Sharma, Wu and Dalal.
+    let pairs = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5, 0.0], [50.0, 0.0, -2.5], 4.3065),
+        ([22.7233, 20.0904, -46.694], [23.0331, 14.973, -42.5619], 2.0373),
+        ([2.0776, 0.0795, -1.135], [0.9033, -0.0636, -0.5514], 0.9082),
+    ];
+    for (lab1, lab2, expected) in pairs {
+        let delta_e = fitness::delta_e2000(lab1, lab2);
+        assert!((delta_e - expected).abs() < 1e-4, "{lab1:?} and {lab2:?}: {delta_e} instead of {expected}");
+        assert!((fitness::delta_e2000(lab2, lab1) - delta_e).abs() < 1e-9);
+    }
+
+    let white = fitness::srgb_to_lab([255, 255, 255]);
+    assert!((white[0] - 100.0).abs() < 1e-3 && white[1].abs() < 1e-3 && white[2].abs() < 1e-3, "{white:?}");
+}
+
+#[test]
+fn incremental_fitness_matches_full_distance_for_every_metric() {
+    let target = common::gradient_target(48, 32);
+    for metric in [MetricKind::RgbL2, MetricKind::LuminanceWeighted, MetricKind::DeltaE76, MetricKind::DeltaE2000, MetricKind::Ssim] {
+        let config = PainterConfig { metric, ..common::seeded_config(2, 5) };
+        let (approx, _) = svg_painter::run(&target, &config, |_| {});
+        assert!(approx.stroke_count() > 0, "no strokes with {metric:?}");
+
+        let expected = fitness::distance(fitness::metric_for(&config).as_ref(), approx.get_render(), &target, None).unwrap();
+        assert!((expected - approx.get_fitness()).abs() < 1e-6, "{metric:?}: {expected} vs {}", approx.get_fitness());
+    }
+}
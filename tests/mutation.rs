@@ -0,0 +1,60 @@
+use svg_painter::Controller;
+use svg_painter::config::{MutationWeights, PainterConfig};
+use svg_painter::primitive::Primitive;
+use svg_painter::stroke::{Mutation, Stroke};
+
+fn controller_for(config: &PainterConfig) -> Controller {
+    let mask = tiny_skia::Pixmap::new(50, 50).unwrap();
+    let mut controller = Controller::new(&mask, config);
+    controller.set_scale((0.4, 0.2));
+    controller
+}
+
+fn only(weights: MutationWeights) -> PainterConfig {
+    let mut config = PainterConfig { seed: Some(5), ..PainterConfig::default() };
+    config.mutation.weights = weights;
+    config
+}
+
+#[test]
+fn zero_weight_operators_are_never_picked() {
+    let none = MutationWeights { translate: 0.0, rotate: 0.0, scale: 0.0, template: 0.0, skew: 0.0 };
+    let mut controller = controller_for(&only(MutationWeights { rotate: 1.0, skew: 3.0, ..none.clone() }));
+    let picked = (0..1000).map(|_| controller.pick_mutation()).collect::<Vec<Mutation>>();
+    assert!(picked.iter().all(|m| matches!(m, Mutation::Rotate | Mutation::Skew)));
+    assert!(picked.contains(&Mutation::Rotate) && picked.contains(&Mutation::Skew));
+
+    // Without any positive weight, translations are the fallback.
+    let mut controller = controller_for(&only(none));
+    assert!((0..100).all(|_| controller.pick_mutation() == Mutation::Translate));
+}
+
+#[test]
+fn scale_stays_within_its_bounds() {
+    let mut config = only(MutationWeights { translate: 0.0, rotate: 0.0, scale: 1.0, template: 0.0, skew: 0.0 });
+    config.mutation.scale_step = 0.9;
+    let mut controller = controller_for(&config);
+    let mut stroke = Stroke::new(controller.rng());
+    stroke.set_scale((0.4, 0.2));
+    for _ in 0..500 {
+        stroke.mutate(&mut controller);
+        let (x, y) = stroke.get_scale();
+        assert!((0.2..=0.8).contains(&x) && (0.1..=0.4).contains(&y), "{x} {y}");
+    }
+
+    // Inverted bounds, as a config built in code may have them, must not panic.
+    config.mutation.min_scale = 2.0;
+    config.mutation.max_scale = 0.5;
+    let mut controller = controller_for(&config);
+    for _ in 0..100 {
+        stroke.mutate(&mut controller);
+        let (x, y) = stroke.get_scale();
+        assert!((0.2..=0.8).contains(&x) && (0.1..=0.4).contains(&y), "{x} {y}");
+    }
+
+    // A negative stroke size, as a negative scale_divisor gives, must not panic either.
+    controller.set_scale((-0.4, -0.2));
+    for _ in 0..100 {
+        stroke.mutate(&mut controller);
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use svg_painter::approximation::ImageApproximation;
+use svg_painter::color::Rgba;
+use svg_painter::config::{AlphaFormat, PainterConfig, ShapeWeights};
+use svg_painter::primitive::Primitive;
+use svg_painter::primitive::shapes::{Circle, Ellipse, Polygon, QuadraticLine, Rectangle, Triangle};
+use svg_painter::stroke::{self, Stroke};
+use svg_painter::util;
+
+const MAX_CHANNEL_DIFF: i32 = 8;
+const MAX_DIFFERING_PIXELS_RATIO: f64 = 0.005;
+
+fn assert_renders_match(stroke: &dyn Primitive, width: u32, height: u32, alpha_format: AlphaFormat) {
+    let mut approx = ImageApproximation::new(tiny_skia::Pixmap::new(width, height).unwrap());
+    approx.set_alpha_format(alpha_format);
+
+    let mut svg_render = tiny_skia::Pixmap::new(width, height).unwrap();
+    util::render_svg_into_pixmap(&approx.express_stroke(stroke), &mut svg_render).unwrap();
+
+    let mut path_render = tiny_skia::Pixmap::new(width, height).unwrap();
+    stroke.render(&mut path_render);
+
+    let mut differing = 0;
+    for (p1, p2) in svg_render.pixels().iter().zip(path_render.pixels()) {
+        let diff = [
+            p1.red() as i32 - p2.red() as i32,
+            p1.green() as i32 - p2.green() as i32,
+            p1.blue() as i32 - p2.blue() as i32,
+            p1.alpha() as i32 - p2.alpha() as i32,
+        ].iter().map(|d| d.abs()).max().unwrap();
+        if diff > MAX_CHANNEL_DIFF {
+            differing += 1;
+        }
+    }
+    let ratio = differing as f64 / (width * height) as f64;
+    assert!(ratio <= MAX_DIFFERING_PIXELS_RATIO, "{differing} pixels differ between SVG and path render of stroke {}", stroke.express());
+}
+
+#[test]
+fn path_render_matches_svg_render() {
+    for stroke_idx in 0..stroke::STROKES.len() {
+        for (xy, rotation, skew, scale) in [((60, 40), 0, 0.0, (0.5, 0.5)), ((30, 50), 45, 0.0, (0.8, 0.3)), ((100, 10), 300, 0.0, (1.3, 1.3)), ((50, 40), 120, -15.5, (0.6, 0.4))] {
+            let mut stroke = Stroke::new(&mut rand::thread_rng());
+            stroke.set_stroke_idx(stroke_idx);
+            stroke.set_xy(xy);
+            stroke.set_rotation(rotation);
+            stroke.set_skew(skew);
+            stroke.set_scale(scale);
+            stroke.set_color(Rgba::new(200, 120, 30, 255));
+            assert_renders_match(&stroke, 120, 80, AlphaFormat::default());
+        }
+    }
+}
+
+#[test]
+fn translucent_path_render_matches_svg_render() {
+    for alpha_format in [AlphaFormat::FillOpacity, AlphaFormat::Hex] {
+        for alpha in [1, 77, 128, 254] {
+            let mut stroke = Stroke::new(&mut rand::thread_rng());
+            stroke.set_xy((60, 40));
+            stroke.set_rotation(30);
+            stroke.set_scale((0.7, 0.5));
+            stroke.set_color(Rgba::new(40, 160, 220, alpha));
+            assert_renders_match(&stroke, 120, 80, alpha_format);
+        }
+    }
+}
+
+#[test]
+fn shape_path_render_matches_svg_render() {
+    let color = Rgba::new(90, 200, 60, 255);
+    let shapes: Vec<Box<dyn Primitive>> = vec![
+        Box::new(Triangle::new([(10.5, 12.0), (100.0, 30.25), (40.0, 70.0)], color.clone())),
+        Box::new(Rectangle::new((60.0, 40.0), (70.5, 30.0), 33.0, color.clone())),
+        Box::new(Ellipse::new((50.0, 45.0), (40.0, 18.5), 120.0, color.clone())),
+        Box::new(Circle::new((70.25, 35.5), 27.0, color.clone())),
+        Box::new(QuadraticLine::new([(10.0, 70.0), (60.0, -20.0), (110.0, 60.0)], 7.5, color.clone())),
+        Box::new(Polygon::new(vec![(20.0, 10.0), (90.0, 15.0), (110.0, 50.0), (60.0, 75.0), (15.0, 60.0)], color.clone())),
+        Box::new(Circle::new((40.0, 40.0), 20.0, Rgba::new(90, 200, 60, 120))),
+        Box::new(QuadraticLine::new([(10.0, 10.0), (60.0, 90.0), (110.0, 10.0)], 12.0, Rgba::new(90, 200, 60, 120))),
+    ];
+    for shape in &shapes {
+        assert_renders_match(shape.as_ref(), 120, 80, AlphaFormat::default());
+    }
+}
+
+#[test]
+fn mixed_shape_run_renders_like_its_svg() {
+    let (width, height) = (120, 80);
+    let target = common::gradient_target(width, height);
+    let config = PainterConfig {
+        shapes: ShapeWeights { stroke: 1.0, triangle: 1.0, rectangle: 1.0, ellipse: 1.0, circle: 1.0, line: 1.0, polygon: 1.0 },
+        ..common::seeded_config(5, 40)
+    };
+    let (approx, _) = svg_painter::run(&target, &config, |_| {});
+
+    let mut svg_render = tiny_skia::Pixmap::new(width, height).unwrap();
+    util::render_svg_into_pixmap(&approx.express(), &mut svg_render).unwrap();
+    let svg_fitness = util::pixmap_distance(&svg_render, &target).unwrap();
+    assert!((svg_fitness - approx.get_fitness()).abs() <= approx.get_fitness() * 0.01, "{svg_fitness} vs {}", approx.get_fitness());
+}
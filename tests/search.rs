@@ -0,0 +1,91 @@
+mod common;
+
+use svg_painter::Controller;
+use svg_painter::approximation::{Candidate, ImageApproximation};
+use svg_painter::color::Rgba;
+use svg_painter::config::{PainterConfig, TemperatureSchedule};
+use svg_painter::primitive::{self, Primitive, Shape};
+use svg_painter::search::{Genetic, SimulatedAnnealing};
+
+#[test]
+fn temperature_schedules_run_from_initial_to_final() {
+    for schedule in [TemperatureSchedule::Exponential, TemperatureSchedule::Linear] {
+        let annealing = SimulatedAnnealing::new(50.0, 0.5, schedule, 100);
+        assert!((annealing.temperature(0.0) - 50.0).abs() < 1e-9);
+        assert!((annealing.temperature(1.0) - 0.5).abs() < 1e-9);
+        assert!(annealing.temperature(0.5) < annealing.temperature(0.25));
+    }
+}
+
+#[test]
+fn annealing_accepts_worse_candidates_only_while_hot() {
+    let annealing = SimulatedAnnealing::new(100.0, 0.1, TemperatureSchedule::Exponential, 200);
+    assert_eq!(annealing.acceptance_probability(-5.0, 0.0), 1.0);
+    assert_eq!(annealing.acceptance_probability(-5.0, 1.0), 1.0);
+
+    // A candidate worse by 50 per pixel is accepted more often than not at first, and practically never at the end.
+    assert!(annealing.acceptance_probability(50.0, 0.0) > 0.6);
+    assert!(annealing.acceptance_probability(50.0, 1.0) < 1e-200);
+    assert!(annealing.acceptance_probability(50.0, 0.5) < annealing.acceptance_probability(50.0, 0.25));
+    assert!(annealing.acceptance_probability(10.0, 0.5) > annealing.acceptance_probability(50.0, 0.5));
+}
+
+#[test]
+fn genetic_generations_keep_their_elites() {
+    let target = common::pattern_target(64, 48);
+    let config = PainterConfig { seed: Some(11), ..PainterConfig::default() };
+    let mut controller = Controller::new(&target, &config);
+    controller.set_scale((0.2, 0.2));
+    let approx = ImageApproximation::new(target);
+
+    let founders = (0..8).map(|_| {
+        let mut primitive = primitive::new_primitive(Shape::Stroke, approx.get_brushes(), &mut controller);
+        primitive.set_color(Rgba::new_black());
+        primitive
+    }).collect::<Vec<Box<dyn Primitive>>>();
+    let mut population = approx.evaluate_candidates(founders, controller.get_color_solving()).into_iter().flatten().collect::<Vec<Candidate>>();
+
+    let genetic = Genetic::new(8, 10, 3, 2, 0.7, 0.5);
+    for _ in 0..10 {
+        population.sort_by_key(|(_, render)| render.get_error_sum());
+        let elites = population.iter().take(2).map(|(p, r)| (p.express(), r.get_error_sum())).collect::<Vec<(String, u64)>>();
+
+        population = genetic.next_generation(&approx, population, &mut controller);
+        let survivors = population.iter().take(2).map(|(p, r)| (p.express(), r.get_error_sum())).collect::<Vec<(String, u64)>>();
+        assert_eq!(survivors, elites);
+        // The best candidate is among the elites, so the best error never gets worse.
+        assert!(population.iter().map(|(_, render)| render.get_error_sum()).min().unwrap() <= elites[0].1);
+    }
+}
+
+#[test]
+fn crossover_mixes_parents_of_the_same_shape() {
+    let target = common::pattern_target(64, 48);
+    let config = PainterConfig { seed: Some(12), polygon_vertices: 6, ..PainterConfig::default() };
+    let mut controller = Controller::new(&target, &config);
+    controller.set_scale((0.2, 0.2));
+    let approx = ImageApproximation::new(target);
+
+    for shape in Shape::ALL {
+        let mut first = primitive::new_primitive(shape, approx.get_brushes(), &mut controller);
+        first.set_color(Rgba::new(0, 0, 0, 100));
+        let mut second = primitive::new_primitive(shape, approx.get_brushes(), &mut controller);
+        second.set_color(Rgba::new(0, 0, 0, 200));
+        let geometry = |p: &dyn Primitive| {
+            let mut p = p.box_clone();
+            p.set_color(Rgba::new_black());
+            p.express()
+        };
+
+        let children = (0..50).map(|_| first.crossover(second.as_ref(), &mut controller).unwrap()).collect::<Vec<Box<dyn Primitive>>>();
+        let alphas = children.iter().map(|c| c.get_color().a).collect::<Vec<u8>>();
+        assert!(alphas.iter().all(|a| [100, 200].contains(a)) && alphas.contains(&100) && alphas.contains(&200), "{shape:?}");
+        // Some children combine the geometry of both parents instead of copying one of them.
+        let parents = [geometry(first.as_ref()), geometry(second.as_ref())];
+        assert!(children.iter().any(|c| !parents.contains(&geometry(c.as_ref()))), "{shape:?}");
+
+        let other = if shape == Shape::Circle { Shape::Rectangle } else { Shape::Circle };
+        let other = primitive::new_primitive(other, approx.get_brushes(), &mut controller);
+        assert!(first.crossover(other.as_ref(), &mut controller).is_none(), "{shape:?}");
+    }
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use svg_painter::approximation::ImageApproximation;
+use svg_painter::config::PainterConfig;
+
+#[test]
+fn same_seed_produces_same_svg() {
+    let target = common::pattern_target(64, 48);
+    let mut config = PainterConfig {
+        seed: Some(42),
+        ..PainterConfig::default()
+    };
+    config.stop.max_strokes = Some(10);
+
+    let (first, _) = svg_painter::run(&target, &config, |_| {});
+    let (second, _) = svg_painter::run(&target, &config, |_| {});
+    assert_eq!(first.express(), second.express());
+}
+
+// FNV-1a, whose value, unlike the one of the std hashers, is fixed across Rust versions.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[test]
+fn batches_are_reproducible_and_no_worse_than_single_candidates() {
+    let target = common::pattern_target(40, 30);
+    let batched = |seed| PainterConfig { batch_size: 4, ..common::seeded_config(seed, 20) };
+    let runs = (0..4).map(|seed| svg_painter::run(&target, &batched(seed), |_| {}).0).collect::<Vec<ImageApproximation>>();
+    let (again, _) = svg_painter::run(&target, &batched(0), |_| {});
+    assert_eq!(runs[0].express(), again.express());
+
+    // Batches evaluate more candidates per attempt, so over a few seeds they paint at least as well.
+    let batched_fitness = runs.iter().map(|approx| approx.get_fitness()).sum::<f64>();
+    let single_fitness = (0..4).map(|seed| common::seeded_run(&target, seed, 20).get_fitness()).sum::<f64>();
+    assert!(batched_fitness <= single_fitness, "{batched_fitness} vs {single_fitness}");
+
+    // Batches are evaluated in order whether or not they are spread across threads, so the `parallel` feature
+    // must not change the SVG.
+    assert_eq!(fnv1a(&runs[0].express()), 0x57bba2ca4f69479b);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use svg_painter::approximation::ColorSolving;
+use svg_painter::config::{ColorEstimator, PainterConfig};
+use svg_painter::stroke::Stroke;
+use rand::{Rng, SeedableRng};
+
+fn error_with(approx: &svg_painter::approximation::ImageApproximation, stroke: &Stroke, estimator: ColorEstimator, solve_alpha: bool) -> u64 {
+    let (_, render) = approx.evaluate_candidate(Box::new(stroke.clone()), ColorSolving { estimator, solve_alpha, alpha_range: (1, 255) }).unwrap();
+    render.get_error_sum()
+}
+
+#[test]
+fn least_squares_colors_do_not_increase_the_error() {
+    let (width, height) = (64, 48);
+    let target = common::gradient_target(width, height);
+    // Strokes larger than the image paint the whole canvas, so translucent strokes have something to blend with.
+    let config = PainterConfig { scale_divisor: 0.5, ..common::seeded_config(3, 10) };
+    let (approx, _) = svg_painter::run(&target, &config, |_| {});
+
+    let mut rng = svg_painter::PainterRng::seed_from_u64(11);
+    for _ in 0..20 {
+        let mut stroke = Stroke::new(&mut rng);
+        stroke.set_xy((rng.gen_range(0..width as i32), rng.gen_range(0..height as i32)));
+        stroke.set_rotation(rng.gen_range(0..360));
+        stroke.set_scale((0.2, 0.2));
+
+        let coverage = error_with(&approx, &stroke, ColorEstimator::Coverage, false);
+        let opaque = error_with(&approx, &stroke, ColorEstimator::LeastSquares, false);
+        let translucent = error_with(&approx, &stroke, ColorEstimator::LeastSquares, true);
+        // The solution is exact for the compositing model, tiny-skia only deviates from it by rounding.
+        assert!(opaque <= coverage + coverage / 1000);
+        assert!(translucent <= opaque + opaque / 1000);
+    }
+}
+
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use svg_painter::termination::{Progress, StopConditions, StopReason};
+
+fn progress() -> Progress {
+    Progress {
+        strokes: 10,
+        fitness: 500.0,
+        stage: 2,
+        iterations_without_improvement: 5,
+        elapsed: Duration::from_secs(3),
+    }
+}
+
+#[test]
+fn no_conditions_never_stop() {
+    assert_eq!(StopConditions::default().check(&progress()), None);
+}
+
+#[test]
+fn each_condition_stops_at_its_limit() {
+    let cases = [
+        (StopConditions { max_strokes: Some(10), ..StopConditions::default() }, StopReason::MaxStrokes),
+        (StopConditions { target_fitness: Some(500.0), ..StopConditions::default() }, StopReason::TargetFitness),
+        (StopConditions { max_stages: Some(1), ..StopConditions::default() }, StopReason::MaxStages),
+        (StopConditions { max_iterations_without_improvement: Some(5), ..StopConditions::default() }, StopReason::NoImprovement),
+        (StopConditions { time_budget: Some(Duration::from_secs(3)), ..StopConditions::default() }, StopReason::TimeBudget),
+    ];
+    for (stop, reason) in cases {
+        assert_eq!(stop.check(&progress()), Some(reason));
+    }
+}
+
+#[test]
+fn conditions_below_their_limit_do_not_stop() {
+    let stop = StopConditions {
+        max_strokes: Some(11),
+        target_fitness: Some(499.0),
+        time_budget: Some(Duration::from_secs(4)),
+        max_stages: Some(2),
+        max_iterations_without_improvement: Some(6),
+    };
+    assert_eq!(stop.check(&progress()), None);
+}
+
+#[test]
+fn reason_codes() {
+    assert_eq!(StopReason::MaxStrokes.code(), "MAX_STROKES");
+    assert_eq!(StopReason::TargetFitness.code(), "TARGET_FITNESS");
+    assert_eq!(StopReason::TimeBudget.code(), "TIME_BUDGET");
+    assert_eq!(StopReason::MaxStages.code(), "MAX_STAGES");
+    assert_eq!(StopReason::NoImprovement.code(), "NO_IMPROVEMENT");
+}
+
+#[test]
+fn time_budget_includes_hook_time() {
+    let mut target = tiny_skia::Pixmap::new(32, 32).unwrap();
+    target.fill(tiny_skia::Color::from_rgba8(200, 40, 90, 255));
+    let mut config = svg_painter::config::PainterConfig { seed: Some(1), ..Default::default() };
+    config.stop.time_budget = Some(Duration::from_millis(200));
+
+    // Most insertions succeed on a flat target, so without the hook time the run would go on far longer.
+    let mut hook_calls = 0;
+    let (_, reason) = svg_painter::run(&target, &config, |_| {
+        hook_calls += 1;
+        std::thread::sleep(Duration::from_millis(100));
+    });
+    assert_eq!(reason, StopReason::TimeBudget);
+    assert!(hook_calls <= 3, "{hook_calls} hook calls");
+}